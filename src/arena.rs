@@ -1,4 +1,7 @@
-use std::{fmt::Display, marker::PhantomData};
+use {
+    crate::*,
+    std::{fmt::Display, marker::PhantomData},
+};
 
 #[derive(Debug, Clone, Hash)]
 pub struct Id<T> {
@@ -20,34 +23,224 @@ impl<T> Display for Id<T> {
     }
 }
 
+// a node used as the input of another node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Expr(Id<Expr>),
+    Ctrl(Id<Ctrl>),
+}
+
+pub trait Node: Sized {
+    // this node as the input of another node
+    fn input(id: Id<Self>) -> Input;
+
+    // the nodes this one uses, in order
+    fn inputs(&self) -> Vec<Input>;
+
+    // replace the input at position `index` of `inputs`
+    fn set_input(&mut self, index: usize, input: Input);
+
+    // add an input after the existing ones, like a phi value or a loop back edge
+    fn add_input(&mut self, input: Input);
+}
+
+// each arena keeps track of which of its own nodes use every node, so the users of a node are
+// found by asking both the expression and the control arena
 #[derive(Debug, Clone)]
 pub struct Arena<T> {
-    nodes: Vec<T>,
+    // killed nodes leave a hole so ids stay valid
+    nodes: Vec<Option<T>>,
+    // for every expression and control, the nodes in this arena using it, once per use
+    expr_users: Vec<Vec<usize>>,
+    ctrl_users: Vec<Vec<usize>>,
 }
 
-impl<T> Arena<T> {
+impl<T: Node> Arena<T> {
     pub fn new() -> Self {
-        Self { nodes: Vec::new() }
+        Self {
+            nodes: Vec::new(),
+            expr_users: Vec::new(),
+            ctrl_users: Vec::new(),
+        }
     }
 
     pub fn alloc(&mut self, value: T) -> Id<T> {
         let index = self.nodes.len();
-        self.nodes.push(value);
-        Id {
-            index,
-            _marker: PhantomData,
+        for input in value.inputs() {
+            self.users_mut(input).push(index);
         }
+        self.nodes.push(Some(value));
+        Self::id(index)
     }
 
     pub fn get(&self, id: &Id<T>) -> &T {
-        &self.nodes[id.index]
+        self.nodes[id.index].as_ref().expect("use of a killed node")
     }
 
-    pub fn get_mut(&mut self, id: &Id<T>) -> &mut T {
-        &mut self.nodes[id.index]
+    // only for what isn't an input, like symbols, the inputs are changed with `set_input`,
+    // `add_input` and `replace` to keep the users up to date
+    pub(crate) fn get_mut(&mut self, id: &Id<T>) -> &mut T {
+        self.nodes[id.index].as_mut().expect("use of a killed node")
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.nodes.iter_mut().flatten()
     }
 
     pub fn clear(&mut self) {
         self.nodes.clear();
+        self.expr_users.clear();
+        self.ctrl_users.clear();
+    }
+
+    // the nodes in this arena using `input`, once per use
+    pub fn users(&self, input: Input) -> Vec<Id<T>> {
+        let users = match input {
+            Input::Expr(id) => self.expr_users.get(id.index),
+            Input::Ctrl(id) => self.ctrl_users.get(id.index),
+        };
+        users
+            .map(|users| users.iter().map(|index| Self::id(*index)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn set_input(&mut self, id: Id<T>, index: usize, input: Input) {
+        let old = self.get(&id).inputs()[index];
+        self.remove_user(old, id.index);
+        self.get_mut(&id).set_input(index, input);
+        self.users_mut(input).push(id.index);
+    }
+
+    pub fn add_input(&mut self, id: Id<T>, input: Input) {
+        self.get_mut(&id).add_input(input);
+        self.users_mut(input).push(id.index);
+    }
+
+    // make every node in this arena that uses `old` use `new` instead
+    pub fn replace(&mut self, old: Input, new: Input) {
+        if old == new {
+            return;
+        }
+        let mut users = match old {
+            Input::Expr(id) => self.expr_users.get_mut(id.index).map(std::mem::take),
+            Input::Ctrl(id) => self.ctrl_users.get_mut(id.index).map(std::mem::take),
+        }
+        .unwrap_or_default();
+        users.sort_unstable();
+        users.dedup();
+        for index in users {
+            let node = self.nodes[index].as_mut().unwrap();
+            let mut uses = 0;
+            for (position, input) in node.inputs().into_iter().enumerate() {
+                if input == old {
+                    node.set_input(position, new);
+                    uses += 1;
+                }
+            }
+            self.users_mut(new).extend(std::iter::repeat_n(index, uses));
+        }
+    }
+
+    // remove a node nothing uses anymore, releasing its inputs; users in the other arena are
+    // checked by the caller
+    pub fn kill(&mut self, id: Id<T>) {
+        debug_assert!(
+            self.users(T::input(Self::id(id.index))).is_empty(),
+            "killed node {} is still used",
+            id
+        );
+        let node = self.nodes[id.index].take().expect("node killed twice");
+        for input in node.inputs() {
+            self.remove_user(input, id.index);
+        }
+    }
+
+    fn id(index: usize) -> Id<T> {
+        Id {
+            index,
+            _marker: PhantomData,
+        }
+    }
+
+    fn users_mut(&mut self, input: Input) -> &mut Vec<usize> {
+        let (users, index) = match input {
+            Input::Expr(id) => (&mut self.expr_users, id.index),
+            Input::Ctrl(id) => (&mut self.ctrl_users, id.index),
+        };
+        if users.len() <= index {
+            users.resize_with(index + 1, Vec::new);
+        }
+        &mut users[index]
+    }
+
+    fn remove_user(&mut self, input: Input, index: usize) {
+        let users = self.users_mut(input);
+        if let Some(position) = users.iter().position(|user| *user == index) {
+            users.swap_remove(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    const I64: Type = Type::Int(IntType::I64);
+
+    fn constant(exprs: &mut Arena<Expr>, value: i64) -> Id<Expr> {
+        exprs.alloc(Expr::Constant {
+            value: Value::Int(IntValue::Constant(value)),
+            ty: I64,
+        })
+    }
+
+    fn add(exprs: &mut Arena<Expr>, lhs_id: Id<Expr>, rhs_id: Id<Expr>) -> Id<Expr> {
+        exprs.alloc(Expr::Binary {
+            lhs_id,
+            op: BinaryOp::Add,
+            rhs_id,
+            ty: I64,
+        })
+    }
+
+    #[test]
+    fn users() {
+        let mut exprs = Arena::new();
+        let (a, b) = (constant(&mut exprs, 1), constant(&mut exprs, 2));
+        let sum = add(&mut exprs, a, a);
+        assert_eq!(exprs.users(Input::Expr(a)), vec![sum, sum]);
+
+        exprs.set_input(sum, 1, Input::Expr(b));
+        assert_eq!(exprs.users(Input::Expr(a)), vec![sum]);
+        assert_eq!(exprs.users(Input::Expr(b)), vec![sum]);
+
+        let other = add(&mut exprs, b, a);
+        exprs.replace(Input::Expr(a), Input::Expr(b));
+        assert!(exprs.users(Input::Expr(a)).is_empty());
+        assert_eq!(exprs.users(Input::Expr(b)).len(), 4);
+        assert!(matches!(
+            exprs.get(&other),
+            Expr::Binary { lhs_id, rhs_id, .. } if *lhs_id == b && *rhs_id == b
+        ));
+    }
+
+    #[test]
+    fn kill() {
+        let mut exprs = Arena::new();
+        let a = constant(&mut exprs, 1);
+        let sum = add(&mut exprs, a, a);
+        exprs.kill(sum);
+        assert!(exprs.users(Input::Expr(a)).is_empty());
+        exprs.kill(a);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "still used")]
+    fn kill_used() {
+        let mut exprs = Arena::new();
+        let a = constant(&mut exprs, 1);
+        add(&mut exprs, a, a);
+        exprs.kill(a);
     }
 }
@@ -1,8 +1,15 @@
 use ramhas::*;
 
 fn main() {
-    let mut parser =
-        //Parser::new("int a = 1; int b = 2; int c = 0; { int b = 5; c = a + b; } { int e = 6; c = a + e; } return c;");
-        Parser::new("int arg = 9; int a = 1; if (arg == 1) a = arg + 2; else a = arg - 3; return a;");
-    parser.parse_program();
+    //let source = "fn main() -> int { int a = 1; int b = 2; int c = 0; { int b = 5; c = a + b; } { int e = 6; c = a + e; } return c; }";
+    let source = "fn main(int arg) -> int { int a = 1; if (arg == 1) a = arg + 2; else a = arg - 3; return a; }";
+    let mut parser = Parser::new(source);
+    let renderer = Renderer::colored(source);
+    let diagnostics = match parser.parse_program() {
+        Ok(_) => parser.diagnostics().to_vec(),
+        Err(diagnostics) => diagnostics,
+    };
+    for diagnostic in diagnostics.iter() {
+        eprintln!("{}", renderer.render(diagnostic));
+    }
 }
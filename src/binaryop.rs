@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use {crate::*, std::fmt::Display};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryOp {
@@ -22,6 +22,89 @@ pub enum BinaryOp {
     GreaterThanOrEqual,
 }
 
+impl BinaryOp {
+    // result type of the operator, or None if it can't be applied to these operand types
+    pub fn ty(&self, lhs: Type, rhs: Type) -> Option<Type> {
+        match self {
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo => match (lhs, rhs) {
+                (Type::Int(_), Type::Int(_)) | (Type::Float(_), Type::Float(_)) if lhs == rhs => {
+                    Some(lhs)
+                }
+                _ => None,
+            },
+            // the shift amount can be any integer type
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => match (lhs, rhs) {
+                (Type::Int(_), Type::Int(_)) => Some(lhs),
+                _ => None,
+            },
+            BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => match lhs {
+                Type::Bool | Type::Int(_) if lhs == rhs => Some(lhs),
+                _ => None,
+            },
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => {
+                if lhs == Type::Bool && rhs == Type::Bool {
+                    Some(Type::Bool)
+                } else {
+                    None
+                }
+            }
+            BinaryOp::Equal | BinaryOp::NotEqual => {
+                if lhs == rhs {
+                    Some(Type::Bool)
+                } else {
+                    None
+                }
+            }
+            BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessThanOrEqual
+            | BinaryOp::GreaterThanOrEqual => match (lhs, rhs) {
+                (Type::Int(_), Type::Int(_)) | (Type::Float(_), Type::Float(_)) if lhs == rhs => {
+                    Some(Type::Bool)
+                }
+                _ => None,
+            },
+        }
+    }
+
+    // result type to continue with when the operand types are invalid
+    pub fn fallback_ty(&self, lhs: Type) -> Type {
+        match self {
+            BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => lhs,
+            BinaryOp::LogicalAnd
+            | BinaryOp::LogicalOr
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessThanOrEqual
+            | BinaryOp::GreaterThanOrEqual => Type::Bool,
+            _ => match lhs {
+                Type::Int(_) | Type::Float(_) => lhs,
+                _ => Type::Int(IntType::I64),
+            },
+        }
+    }
+
+    // the comparison that is true exactly when this one is false, except for floats where both
+    // are false when either side is NaN
+    pub fn inverse(&self) -> Option<BinaryOp> {
+        match self {
+            BinaryOp::Equal => Some(BinaryOp::NotEqual),
+            BinaryOp::NotEqual => Some(BinaryOp::Equal),
+            BinaryOp::LessThan => Some(BinaryOp::GreaterThanOrEqual),
+            BinaryOp::GreaterThan => Some(BinaryOp::LessThanOrEqual),
+            BinaryOp::LessThanOrEqual => Some(BinaryOp::GreaterThan),
+            BinaryOp::GreaterThanOrEqual => Some(BinaryOp::LessThan),
+            _ => None,
+        }
+    }
+}
+
 impl Display for BinaryOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
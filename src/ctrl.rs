@@ -17,8 +17,6 @@ pub enum Ctrl {
     If {
         ctrl_id: Id<Ctrl>,
         expr_id: Id<Expr>,
-        then_id: Id<Ctrl>,
-        else_id: Option<Id<Ctrl>>,
     },
     Then {
         ctrl_id: Id<Ctrl>,
@@ -30,7 +28,133 @@ pub enum Ctrl {
     },
     Merge {
         ctrl_ids: Vec<Id<Ctrl>>,
+        symbols: Symbols,
+    },
+    Loop {
+        ctrl_id: Id<Ctrl>,
+        back_id: Option<Id<Ctrl>>,
+        // the value of every variable at the top of the loop, lazy until it is first used
+        phis: Symbols,
+        symbols: Symbols,
+    },
+    // the callee is resolved once all functions are parsed
+    Call {
+        ctrl_id: Id<Ctrl>,
+        name: String,
+        start_id: Option<Id<Ctrl>>,
+        arg_ids: Vec<Id<Expr>>,
     },
+    CallEnd {
+        ctrl_id: Id<Ctrl>,
+        symbols: Symbols,
+    },
+    // unreachable, after return, break or continue, or on a branch that is never taken
+    Dead {
+        symbols: Symbols,
+    },
+}
+
+impl Node for Ctrl {
+    fn input(id: Id<Ctrl>) -> Input {
+        Input::Ctrl(id)
+    }
+
+    // the arguments of Start are its projections, not inputs
+    fn inputs(&self) -> Vec<Input> {
+        match self {
+            Ctrl::Start { .. } | Ctrl::Dead { .. } => Vec::new(),
+            Ctrl::Return { ctrl_id, expr_id } | Ctrl::If { ctrl_id, expr_id } => {
+                vec![Input::Ctrl(*ctrl_id), Input::Expr(*expr_id)]
+            }
+            Ctrl::Stop { ctrl_ids } | Ctrl::Merge { ctrl_ids, .. } => ctrl_ids
+                .iter()
+                .map(|ctrl_id| Input::Ctrl(*ctrl_id))
+                .collect(),
+            Ctrl::Then { ctrl_id, .. }
+            | Ctrl::Else { ctrl_id, .. }
+            | Ctrl::CallEnd { ctrl_id, .. } => {
+                vec![Input::Ctrl(*ctrl_id)]
+            }
+            Ctrl::Loop {
+                ctrl_id, back_id, ..
+            } => std::iter::once(ctrl_id)
+                .chain(back_id)
+                .map(|ctrl_id| Input::Ctrl(*ctrl_id))
+                .collect(),
+            Ctrl::Call {
+                ctrl_id,
+                start_id,
+                arg_ids,
+                ..
+            } => std::iter::once(Input::Ctrl(*ctrl_id))
+                .chain(arg_ids.iter().map(|arg_id| Input::Expr(*arg_id)))
+                .chain(start_id.map(Input::Ctrl))
+                .collect(),
+        }
+    }
+
+    fn set_input(&mut self, index: usize, input: Input) {
+        match (self, index, input) {
+            (
+                Ctrl::Return { ctrl_id, .. }
+                | Ctrl::If { ctrl_id, .. }
+                | Ctrl::Then { ctrl_id, .. }
+                | Ctrl::Else { ctrl_id, .. }
+                | Ctrl::Loop { ctrl_id, .. }
+                | Ctrl::Call { ctrl_id, .. }
+                | Ctrl::CallEnd { ctrl_id, .. },
+                0,
+                Input::Ctrl(id),
+            ) => *ctrl_id = id,
+            (Ctrl::Return { expr_id, .. } | Ctrl::If { expr_id, .. }, 1, Input::Expr(id)) => {
+                *expr_id = id
+            }
+            (Ctrl::Stop { ctrl_ids } | Ctrl::Merge { ctrl_ids, .. }, _, Input::Ctrl(id))
+                if index < ctrl_ids.len() =>
+            {
+                ctrl_ids[index] = id
+            }
+            (
+                Ctrl::Loop {
+                    back_id: Some(back_id),
+                    ..
+                },
+                1,
+                Input::Ctrl(id),
+            ) => *back_id = id,
+            (Ctrl::Call { arg_ids, .. }, _, Input::Expr(id))
+                if index > 0 && index <= arg_ids.len() =>
+            {
+                arg_ids[index - 1] = id
+            }
+            (
+                Ctrl::Call {
+                    arg_ids,
+                    start_id: Some(start_id),
+                    ..
+                },
+                _,
+                Input::Ctrl(id),
+            ) if index == arg_ids.len() + 1 => *start_id = id,
+            (ctrl, _, _) => panic!("{:?} has no input {} of that kind", ctrl, index),
+        }
+    }
+
+    // a loop gets its back edge and a call its callee after they are created
+    fn add_input(&mut self, input: Input) {
+        match (self, input) {
+            (Ctrl::Stop { ctrl_ids } | Ctrl::Merge { ctrl_ids, .. }, Input::Ctrl(id)) => {
+                ctrl_ids.push(id)
+            }
+            (Ctrl::Loop { back_id, .. }, Input::Ctrl(id)) if back_id.is_none() => {
+                *back_id = Some(id)
+            }
+            (Ctrl::Call { start_id, .. }, Input::Ctrl(id)) if start_id.is_none() => {
+                *start_id = Some(id)
+            }
+            (ctrl, _) => panic!("can't add an input to {:?}", ctrl),
+        }
+    }
 }
 
 impl Arena<Ctrl> {
@@ -40,7 +164,11 @@ impl Arena<Ctrl> {
             Ctrl::Start { symbols, .. } => symbols,
             Ctrl::Then { symbols, .. } => symbols,
             Ctrl::Else { symbols, .. } => symbols,
-            _ => panic!("If, Merge and Return have no symbols"),
+            Ctrl::Merge { symbols, .. } => symbols,
+            Ctrl::Loop { symbols, .. } => symbols,
+            Ctrl::CallEnd { symbols, .. } => symbols,
+            Ctrl::Dead { symbols } => symbols,
+            _ => panic!("If, Return, Stop and Call have no symbols"),
         }
     }
 
@@ -50,7 +178,73 @@ impl Arena<Ctrl> {
             Ctrl::Start { symbols, .. } => symbols,
             Ctrl::Then { symbols, .. } => symbols,
             Ctrl::Else { symbols, .. } => symbols,
-            _ => panic!("If, Merge and Return have no symbols"),
+            Ctrl::Merge { symbols, .. } => symbols,
+            Ctrl::Loop { symbols, .. } => symbols,
+            Ctrl::CallEnd { symbols, .. } => symbols,
+            Ctrl::Dead { symbols } => symbols,
+            _ => panic!("If, Return, Stop and Call have no symbols"),
+        }
+    }
+
+    pub fn phis(&self, id: Id<Ctrl>) -> &Symbols {
+        match self.get(&id) {
+            Ctrl::Loop { phis, .. } => phis,
+            _ => panic!("only Loop has phis"),
+        }
+    }
+
+    pub fn phis_mut(&mut self, id: Id<Ctrl>) -> &mut Symbols {
+        match self.get_mut(&id) {
+            Ctrl::Loop { phis, .. } => phis,
+            _ => panic!("only Loop has phis"),
+        }
+    }
+
+    pub fn set_arg_ids(&mut self, start_id: Id<Ctrl>, arg_ids: Vec<Id<Expr>>) {
+        match self.get_mut(&start_id) {
+            Ctrl::Start {
+                arg_ids: start_arg_ids,
+                ..
+            } => *start_arg_ids = arg_ids,
+            _ => panic!("only Start has arguments"),
+        }
+    }
+
+    // whether control can never get here
+    pub fn is_dead(&self, mut id: Id<Ctrl>) -> bool {
+        loop {
+            match self.get(&id) {
+                Ctrl::Dead { .. } => return true,
+                Ctrl::Return { ctrl_id, .. }
+                | Ctrl::If { ctrl_id, .. }
+                | Ctrl::Then { ctrl_id, .. }
+                | Ctrl::Else { ctrl_id, .. }
+                | Ctrl::Loop { ctrl_id, .. }
+                | Ctrl::Call { ctrl_id, .. }
+                | Ctrl::CallEnd { ctrl_id, .. } => id = *ctrl_id,
+                // merges only join live paths
+                Ctrl::Start { .. } | Ctrl::Stop { .. } | Ctrl::Merge { .. } => return false,
+            }
+        }
+    }
+
+    // replace every use of expression `old_id` by `new_id`, including the variables bound to it
+    pub fn replace_expr(&mut self, old_id: Id<Expr>, new_id: Id<Expr>) {
+        self.replace(Input::Expr(old_id), Input::Expr(new_id));
+        for ctrl in self.iter_mut() {
+            match ctrl {
+                Ctrl::Start { symbols, .. }
+                | Ctrl::Then { symbols, .. }
+                | Ctrl::Else { symbols, .. }
+                | Ctrl::Merge { symbols, .. }
+                | Ctrl::Loop { symbols, .. }
+                | Ctrl::CallEnd { symbols, .. }
+                | Ctrl::Dead { symbols } => symbols.replace(old_id, new_id),
+                _ => {}
+            }
+            if let Ctrl::Loop { phis, .. } = ctrl {
+                phis.replace(old_id, new_id);
+            }
         }
     }
 }
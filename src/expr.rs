@@ -5,24 +5,85 @@ pub enum Expr {
     Phi {
         ctrl: Id<Ctrl>,
         expr_ids: Vec<Id<Expr>>,
+        ty: Type,
     },
     Constant {
         value: Value,
+        ty: Type,
     },
     Binary {
         lhs_id: Id<Expr>,
         op: BinaryOp,
         rhs_id: Id<Expr>,
+        ty: Type,
     },
     Unary {
         op: UnaryOp,
         expr_id: Id<Expr>,
+        ty: Type,
     },
+    // value converted to another type with `as`, or implicitly widened
+    Convert {
+        expr_id: Id<Expr>,
+        ty: Type,
+    },
+    // value projected out of a control node, like a function argument out of Start
+    Proj {
+        ctrl_id: Id<Ctrl>,
+        index: usize,
+        ty: Type,
+    },
+}
+
+impl Node for Expr {
+    fn input(id: Id<Expr>) -> Input {
+        Input::Expr(id)
+    }
+
+    fn inputs(&self) -> Vec<Input> {
+        match self {
+            Expr::Phi { ctrl, expr_ids, .. } => std::iter::once(Input::Ctrl(*ctrl))
+                .chain(expr_ids.iter().map(|expr_id| Input::Expr(*expr_id)))
+                .collect(),
+            Expr::Constant { .. } => Vec::new(),
+            Expr::Binary { lhs_id, rhs_id, .. } => {
+                vec![Input::Expr(*lhs_id), Input::Expr(*rhs_id)]
+            }
+            Expr::Unary { expr_id, .. } | Expr::Convert { expr_id, .. } => {
+                vec![Input::Expr(*expr_id)]
+            }
+            Expr::Proj { ctrl_id, .. } => vec![Input::Ctrl(*ctrl_id)],
+        }
+    }
+
+    fn set_input(&mut self, index: usize, input: Input) {
+        match (self, index, input) {
+            (Expr::Phi { ctrl, .. }, 0, Input::Ctrl(id)) => *ctrl = id,
+            (Expr::Phi { expr_ids, .. }, _, Input::Expr(id)) if index > 0 => {
+                expr_ids[index - 1] = id
+            }
+            (Expr::Binary { lhs_id, .. }, 0, Input::Expr(id)) => *lhs_id = id,
+            (Expr::Binary { rhs_id, .. }, 1, Input::Expr(id)) => *rhs_id = id,
+            (Expr::Unary { expr_id, .. } | Expr::Convert { expr_id, .. }, 0, Input::Expr(id)) => {
+                *expr_id = id
+            }
+            (Expr::Proj { ctrl_id, .. }, 0, Input::Ctrl(id)) => *ctrl_id = id,
+            (expr, _, _) => panic!("{:?} has no input {} of that kind", expr, index),
+        }
+    }
+
+    fn add_input(&mut self, input: Input) {
+        match (self, input) {
+            (Expr::Phi { expr_ids, .. }, Input::Expr(id)) => expr_ids.push(id),
+            (expr, _) => panic!("can't add an input to {:?}", expr),
+        }
+    }
 }
 
 impl Arena<Expr> {
     pub fn peephole(&mut self, id: Id<Expr>) -> Id<Expr> {
         let value = self.compute(id);
+        let ty = self.ty(id);
         match value {
             Value::All => {}
             Value::Int(value) => {
@@ -30,13 +91,16 @@ impl Arena<Expr> {
                 if let IntValue::Constant(_) = value {
                     return self.alloc(Expr::Constant {
                         value: Value::Int(value),
+                        ty,
                     });
                 }
                 let expr = self.get(&id);
                 match expr {
                     Expr::Phi { .. } => {}
                     Expr::Constant { .. } => {}
-                    Expr::Binary { lhs_id, op, rhs_id } => {
+                    Expr::Binary {
+                        lhs_id, op, rhs_id, ..
+                    } => {
                         let lhs = self.get(&lhs_id);
                         let rhs = self.get(&rhs_id);
                         match op {
@@ -45,6 +109,7 @@ impl Arena<Expr> {
                                 // const + expr -> expr + const
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(lhs_value)),
+                                    ..
                                 } = lhs
                                 {
                                     if *lhs_value == 0 {
@@ -54,12 +119,14 @@ impl Arena<Expr> {
                                             lhs_id: *rhs_id,
                                             op: BinaryOp::Add,
                                             rhs_id: *lhs_id,
+                                            ty,
                                         });
                                     }
                                 }
                                 // expr + 0 -> expr
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(0)),
+                                    ..
                                 } = rhs
                                 {
                                     return *lhs_id;
@@ -72,16 +139,19 @@ impl Arena<Expr> {
                                 // 0 - expr -> -expr
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(0)),
+                                    ..
                                 } = lhs
                                 {
                                     return self.alloc(Expr::Unary {
                                         op: UnaryOp::Negate,
                                         expr_id: *rhs_id,
+                                        ty,
                                     });
                                 }
                                 // expr - 0 -> expr
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(0)),
+                                    ..
                                 } = rhs
                                 {
                                     return *lhs_id;
@@ -90,18 +160,21 @@ impl Arena<Expr> {
                                 if *lhs_id == *rhs_id {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Int(IntValue::Constant(0)),
+                                        ty,
                                     });
                                 }
                             }
                             BinaryOp::Multiply => {
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(lhs_value)),
+                                    ..
                                 } = lhs
                                 {
                                     // 0 * expr -> 0
                                     if *lhs_value == 0 {
                                         return self.alloc(Expr::Constant {
                                             value: Value::Int(IntValue::Constant(0)),
+                                            ty,
                                         });
                                     }
                                     // 1 * expr -> expr
@@ -113,6 +186,7 @@ impl Arena<Expr> {
                                         return self.alloc(Expr::Unary {
                                             op: UnaryOp::Negate,
                                             expr_id: *rhs_id,
+                                            ty,
                                         });
                                     }
                                     // const * expr -> expr * const
@@ -121,6 +195,7 @@ impl Arena<Expr> {
                                             lhs_id: *rhs_id,
                                             op: BinaryOp::Multiply,
                                             rhs_id: *lhs_id,
+                                            ty,
                                         });
                                     }
                                 }
@@ -129,6 +204,7 @@ impl Arena<Expr> {
                                 // 0 / expr -> 0
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(0)),
+                                    ..
                                 } = lhs
                                 {
                                     return *lhs_id;
@@ -136,6 +212,7 @@ impl Arena<Expr> {
                                 // expr / 1 -> expr
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(1)),
+                                    ..
                                 } = rhs
                                 {
                                     return *lhs_id;
@@ -145,6 +222,7 @@ impl Arena<Expr> {
                                 // 0 % expr -> 0
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(0)),
+                                    ..
                                 } = lhs
                                 {
                                     return *lhs_id;
@@ -152,20 +230,138 @@ impl Arena<Expr> {
                                 // expr % 1 -> 0
                                 if let Expr::Constant {
                                     value: Value::Int(IntValue::Constant(1)),
+                                    ..
                                 } = rhs
                                 {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Int(IntValue::Constant(0)),
+                                        ty,
                                     });
                                 }
                             }
                             BinaryOp::LogicalAnd => {}
                             BinaryOp::LogicalOr => {}
-                            BinaryOp::And => {}
-                            BinaryOp::Or => {}
-                            BinaryOp::Xor => {}
-                            BinaryOp::ShiftLeft => {}
-                            BinaryOp::ShiftRight => {}
+                            BinaryOp::And => {
+                                // expr & expr -> expr
+                                if *lhs_id == *rhs_id {
+                                    return *lhs_id;
+                                }
+                                // const & expr -> expr & const
+                                if let Expr::Constant {
+                                    value: Value::Int(IntValue::Constant(_)),
+                                    ..
+                                } = lhs
+                                {
+                                    return self.alloc(Expr::Binary {
+                                        lhs_id: *rhs_id,
+                                        op: BinaryOp::And,
+                                        rhs_id: *lhs_id,
+                                        ty,
+                                    });
+                                }
+                                // expr & const -> expr, if const only clears bits known to be zero
+                                if let Expr::Constant {
+                                    value: Value::Int(IntValue::Constant(rhs_value)),
+                                    ..
+                                } = rhs
+                                {
+                                    if !(*rhs_value as u64) & !self.known_bits(*lhs_id).zeros == 0 {
+                                        return *lhs_id;
+                                    }
+                                }
+                            }
+                            BinaryOp::Or => {
+                                // expr | expr -> expr
+                                if *lhs_id == *rhs_id {
+                                    return *lhs_id;
+                                }
+                                // const | expr -> expr | const
+                                if let Expr::Constant {
+                                    value: Value::Int(IntValue::Constant(_)),
+                                    ..
+                                } = lhs
+                                {
+                                    return self.alloc(Expr::Binary {
+                                        lhs_id: *rhs_id,
+                                        op: BinaryOp::Or,
+                                        rhs_id: *lhs_id,
+                                        ty,
+                                    });
+                                }
+                                // expr | const -> expr, if const only sets bits known to be one
+                                if let Expr::Constant {
+                                    value: Value::Int(IntValue::Constant(rhs_value)),
+                                    ..
+                                } = rhs
+                                {
+                                    if *rhs_value as u64 & !self.known_bits(*lhs_id).ones == 0 {
+                                        return *lhs_id;
+                                    }
+                                }
+                            }
+                            BinaryOp::Xor => {
+                                // expr ^ expr -> 0
+                                if *lhs_id == *rhs_id {
+                                    return self.alloc(Expr::Constant {
+                                        value: Value::Int(IntValue::Constant(0)),
+                                        ty,
+                                    });
+                                }
+                                // const ^ expr -> expr ^ const
+                                if let Expr::Constant {
+                                    value: Value::Int(IntValue::Constant(_)),
+                                    ..
+                                } = lhs
+                                {
+                                    return self.alloc(Expr::Binary {
+                                        lhs_id: *rhs_id,
+                                        op: BinaryOp::Xor,
+                                        rhs_id: *lhs_id,
+                                        ty,
+                                    });
+                                }
+                                // expr ^ 0 -> expr
+                                if let Expr::Constant {
+                                    value: Value::Int(IntValue::Constant(0)),
+                                    ..
+                                } = rhs
+                                {
+                                    return *lhs_id;
+                                }
+                            }
+                            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => {
+                                // expr << 0 -> expr, also for multiples of the width
+                                if let (
+                                    Expr::Constant {
+                                        value: Value::Int(IntValue::Constant(rhs_value)),
+                                        ..
+                                    },
+                                    Type::Int(int_ty),
+                                ) = (rhs, ty)
+                                {
+                                    if *rhs_value & (int_ty.bits() as i64 - 1) == 0 {
+                                        return *lhs_id;
+                                    }
+                                }
+                                // 0 << expr -> 0
+                                // 0 >> expr -> 0, -1 >> expr -> -1 when the shift is arithmetic
+                                if let (
+                                    Expr::Constant {
+                                        value: Value::Int(IntValue::Constant(lhs_value)),
+                                        ..
+                                    },
+                                    Type::Int(int_ty),
+                                ) = (lhs, ty)
+                                {
+                                    if *lhs_value == 0
+                                        || (*op == BinaryOp::ShiftRight
+                                            && int_ty.signed()
+                                            && *lhs_value == -1)
+                                    {
+                                        return *lhs_id;
+                                    }
+                                }
+                            }
                             BinaryOp::Equal => {}
                             BinaryOp::NotEqual => {}
                             BinaryOp::LessThan => {}
@@ -174,68 +370,129 @@ impl Arena<Expr> {
                             BinaryOp::GreaterThanOrEqual => {}
                         }
                     }
-                    Expr::Unary { .. } => {}
+                    Expr::Unary { op, expr_id, .. } => {
+                        // -(-expr) -> expr
+                        // ~~expr -> expr
+                        if let Expr::Unary {
+                            op: inner_op,
+                            expr_id: inner_id,
+                            ..
+                        } = self.get(expr_id)
+                        {
+                            // unless the inner operator was ill-typed
+                            if inner_op == op && self.ty(*inner_id) == ty {
+                                return *inner_id;
+                            }
+                        }
+                    }
+                    Expr::Convert { expr_id, .. } => {
+                        if let Some(id) = self.collapse(*expr_id, ty) {
+                            return id;
+                        }
+                    }
+                    Expr::Proj { .. } => {}
                 }
             }
             Value::Bool(value) => {
                 if let BoolValue::Constant(_) = value {
                     return self.alloc(Expr::Constant {
                         value: Value::Bool(value),
+                        ty,
                     });
                 }
                 let expr = self.get(&id);
                 match expr {
                     Expr::Phi { .. } => {}
                     Expr::Constant { .. } => {}
-                    Expr::Binary { lhs_id, op, rhs_id } => {
+                    Expr::Binary {
+                        lhs_id, op, rhs_id, ..
+                    } => {
                         let lhs = self.get(&lhs_id);
                         let rhs = self.get(&rhs_id);
                         match op {
-                            BinaryOp::LogicalAnd => {
+                            BinaryOp::LogicalAnd | BinaryOp::And => {
                                 // false && expr -> false
                                 if let Expr::Constant {
                                     value: Value::Bool(BoolValue::Constant(false)),
+                                    ..
+                                } = lhs
+                                {
+                                    return *lhs_id;
+                                }
+                                // true && expr -> expr
+                                if let Expr::Constant {
+                                    value: Value::Bool(BoolValue::Constant(true)),
+                                    ..
                                 } = lhs
+                                {
+                                    return *rhs_id;
+                                }
+                                // expr && true -> expr
+                                if let Expr::Constant {
+                                    value: Value::Bool(BoolValue::Constant(true)),
+                                    ..
+                                } = rhs
                                 {
                                     return *lhs_id;
                                 }
                                 // expr && false -> false
                                 if let Expr::Constant {
                                     value: Value::Bool(BoolValue::Constant(false)),
+                                    ..
                                 } = rhs
                                 {
                                     return *rhs_id;
                                 }
                             }
-                            BinaryOp::LogicalOr => {
+                            BinaryOp::LogicalOr | BinaryOp::Or => {
                                 // true || expr -> true
                                 if let Expr::Constant {
                                     value: Value::Bool(BoolValue::Constant(true)),
+                                    ..
                                 } = lhs
                                 {
                                     return *lhs_id;
                                 }
+                                // false || expr -> expr
+                                if let Expr::Constant {
+                                    value: Value::Bool(BoolValue::Constant(false)),
+                                    ..
+                                } = lhs
+                                {
+                                    return *rhs_id;
+                                }
+                                // expr || false -> expr
+                                if let Expr::Constant {
+                                    value: Value::Bool(BoolValue::Constant(false)),
+                                    ..
+                                } = rhs
+                                {
+                                    return *lhs_id;
+                                }
                                 // expr || true -> true
                                 if let Expr::Constant {
                                     value: Value::Bool(BoolValue::Constant(true)),
+                                    ..
                                 } = rhs
                                 {
                                     return *rhs_id;
                                 }
                             }
                             BinaryOp::Equal => {
-                                // expr == expr -> true
-                                if lhs_id == rhs_id {
+                                // expr == expr -> true, unless expr may be NaN
+                                if lhs_id == rhs_id && self.reflexive(*lhs_id) {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(true)),
+                                        ty,
                                     });
                                 }
                             }
                             BinaryOp::NotEqual => {
-                                // expr != expr -> false
-                                if lhs_id == rhs_id {
+                                // expr != expr -> false, unless expr may be NaN
+                                if lhs_id == rhs_id && self.reflexive(*lhs_id) {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(false)),
+                                        ty,
                                     });
                                 }
                             }
@@ -244,6 +501,7 @@ impl Arena<Expr> {
                                 if lhs_id == rhs_id {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(false)),
+                                        ty,
                                     });
                                 }
                             }
@@ -252,22 +510,25 @@ impl Arena<Expr> {
                                 if lhs_id == rhs_id {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(false)),
+                                        ty,
                                     });
                                 }
                             }
                             BinaryOp::LessThanOrEqual => {
-                                // expr <= expr -> true
-                                if lhs_id == rhs_id {
+                                // expr <= expr -> true, unless expr may be NaN
+                                if lhs_id == rhs_id && self.reflexive(*lhs_id) {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(true)),
+                                        ty,
                                     });
                                 }
                             }
                             BinaryOp::GreaterThanOrEqual => {
-                                // expr >= expr -> true
-                                if lhs_id == rhs_id {
+                                // expr >= expr -> true, unless expr may be NaN
+                                if lhs_id == rhs_id && self.reflexive(*lhs_id) {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(true)),
+                                        ty,
                                     });
                                 }
                             }
@@ -275,40 +536,163 @@ impl Arena<Expr> {
                             BinaryOp::Subtract => {}
                             BinaryOp::Multiply => {}
                             BinaryOp::Divide => {}
+                            BinaryOp::Xor => {
+                                // expr ^ false -> expr
+                                if let Expr::Constant {
+                                    value: Value::Bool(BoolValue::Constant(false)),
+                                    ..
+                                } = rhs
+                                {
+                                    return *lhs_id;
+                                }
+                                // expr ^ expr -> false
+                                if lhs_id == rhs_id {
+                                    return self.alloc(Expr::Constant {
+                                        value: Value::Bool(BoolValue::Constant(false)),
+                                        ty,
+                                    });
+                                }
+                            }
                             BinaryOp::Modulo => {}
-                            BinaryOp::And => {}
-                            BinaryOp::Or => {}
-                            BinaryOp::Xor => {}
                             BinaryOp::ShiftLeft => {}
                             BinaryOp::ShiftRight => {}
                         }
                     }
-                    Expr::Unary { op, expr_id } => {
+                    Expr::Unary { op, expr_id, .. } => {
                         let expr = self.get(&expr_id);
                         match op {
                             UnaryOp::Not => {
                                 // !true -> false
                                 if let Expr::Constant {
                                     value: Value::Bool(BoolValue::Constant(true)),
+                                    ..
                                 } = expr
                                 {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(false)),
+                                        ty,
                                     });
                                 }
                                 // !false -> true
                                 if let Expr::Constant {
                                     value: Value::Bool(BoolValue::Constant(false)),
+                                    ..
                                 } = expr
                                 {
                                     return self.alloc(Expr::Constant {
                                         value: Value::Bool(BoolValue::Constant(true)),
+                                        ty,
                                     });
                                 }
+                                match expr {
+                                    // !!expr -> expr
+                                    Expr::Unary {
+                                        op: UnaryOp::Not,
+                                        expr_id,
+                                        ..
+                                    } => return *expr_id,
+                                    // !(a < b) -> a >= b, not for floats where both are false
+                                    // for NaN
+                                    Expr::Binary {
+                                        lhs_id, op, rhs_id, ..
+                                    } => {
+                                        if let (Some(op), Type::Int(_)) =
+                                            (op.inverse(), self.ty(*lhs_id))
+                                        {
+                                            let (lhs_id, rhs_id) = (*lhs_id, *rhs_id);
+                                            return self.alloc(Expr::Binary {
+                                                lhs_id,
+                                                op,
+                                                rhs_id,
+                                                ty: Type::Bool,
+                                            });
+                                        }
+                                    }
+                                    _ => {}
+                                }
                             }
-                            UnaryOp::Negate => {}
+                            UnaryOp::Negate | UnaryOp::Complement => {}
+                        }
+                    }
+                    Expr::Convert { expr_id, .. } => {
+                        if let Some(id) = self.collapse(*expr_id, ty) {
+                            return id;
                         }
                     }
+                    Expr::Proj { .. } => {}
+                }
+            }
+            Value::Float(value) => {
+                // if float constant, solidify
+                if let FloatValue::Constant(_) = value {
+                    return self.alloc(Expr::Constant {
+                        value: Value::Float(value),
+                        ty,
+                    });
+                }
+                // only rewrites that give the same result for every input under IEEE 754, so
+                // no reassociation, and x + 0.0 stays because it turns -0.0 into 0.0
+                let constant = |id: &Id<Expr>| match self.get(id) {
+                    Expr::Constant {
+                        value: Value::Float(FloatValue::Constant(value)),
+                        ..
+                    } => Some(*value),
+                    _ => None,
+                };
+                match self.get(&id) {
+                    Expr::Binary {
+                        lhs_id, op, rhs_id, ..
+                    } => match (op, constant(lhs_id), constant(rhs_id)) {
+                        // expr * 1.0 -> expr
+                        // expr / 1.0 -> expr
+                        (BinaryOp::Multiply | BinaryOp::Divide, _, Some(1.0)) => {
+                            return *lhs_id;
+                        }
+                        // 1.0 * expr -> expr
+                        (BinaryOp::Multiply, Some(1.0), _) => {
+                            return *rhs_id;
+                        }
+                        // expr + -0.0 -> expr
+                        (BinaryOp::Add, _, Some(rhs_value))
+                            if rhs_value == 0.0 && rhs_value.is_sign_negative() =>
+                        {
+                            return *lhs_id;
+                        }
+                        // -0.0 + expr -> expr
+                        (BinaryOp::Add, Some(lhs_value), _)
+                            if lhs_value == 0.0 && lhs_value.is_sign_negative() =>
+                        {
+                            return *rhs_id;
+                        }
+                        // expr - 0.0 -> expr
+                        (BinaryOp::Subtract, _, Some(rhs_value))
+                            if rhs_value == 0.0 && rhs_value.is_sign_positive() =>
+                        {
+                            return *lhs_id;
+                        }
+                        _ => {}
+                    },
+                    // -(-expr) -> expr
+                    Expr::Unary {
+                        op: UnaryOp::Negate,
+                        expr_id,
+                        ..
+                    } => {
+                        if let Expr::Unary {
+                            op: UnaryOp::Negate,
+                            expr_id: inner_id,
+                            ..
+                        } = self.get(expr_id)
+                        {
+                            return *inner_id;
+                        }
+                    }
+                    Expr::Convert { expr_id, .. } => {
+                        if let Some(id) = self.collapse(*expr_id, ty) {
+                            return id;
+                        }
+                    }
+                    _ => {}
                 }
             }
             Value::Any => {}
@@ -316,58 +700,89 @@ impl Arena<Expr> {
         id
     }
 
+    // (expr as a) as b -> expr as b, if the conversion to a keeps the value
+    // (expr as a) as b -> expr as b, for integers if a is at least as wide as b
+    fn collapse(&mut self, expr_id: Id<Expr>, ty: Type) -> Option<Id<Expr>> {
+        let (inner_id, middle) = match self.get(&expr_id) {
+            Expr::Convert { expr_id, ty } => (*expr_id, *ty),
+            _ => return None,
+        };
+        let from = self.ty(inner_id);
+        let keeps_value =
+            from.widens_to(middle) || matches!((from, middle), (Type::Bool, Type::Int(_)));
+        let keeps_low_bits = match (from, middle, ty) {
+            (Type::Int(_) | Type::Bool, Type::Int(middle), Type::Int(to)) => {
+                middle.bits() >= to.bits()
+            }
+            _ => false,
+        };
+        if !keeps_value && !keeps_low_bits {
+            return None;
+        }
+        if from == ty {
+            return Some(inner_id);
+        }
+        Some(self.alloc(Expr::Convert {
+            expr_id: inner_id,
+            ty,
+        }))
+    }
+
+    // whether the value is equal to itself, which a NaN float isn't
+    fn reflexive(&self, expr_id: Id<Expr>) -> bool {
+        match self.compute(expr_id) {
+            Value::Float(value) => value.is_nan() == Some(false),
+            _ => true,
+        }
+    }
+
+    // bits of an integer expression that are known at compile time
+    fn known_bits(&self, expr_id: Id<Expr>) -> KnownBits {
+        match self.compute(expr_id) {
+            Value::Int(value) => value.known_bits().unwrap_or(KnownBits::UNKNOWN),
+            _ => KnownBits::UNKNOWN,
+        }
+    }
+
     pub fn compute(&self, expr_id: Id<Expr>) -> Value {
         let expr = self.get(&expr_id);
         match expr {
-            Expr::Phi { expr_ids, .. } => {
-                let mut value = Value::Any;
+            Expr::Phi { expr_ids, ty, .. } => {
+                // a loop phi whose back edge isn't known yet can be anything
+                if expr_ids.len() < 2 {
+                    return ty.any();
+                }
+                // only look at constant inputs, anything else (including a loop back edge that
+                // depends on the phi itself) can be anything
+                let mut value = Value::All;
                 for id in expr_ids.iter() {
-                    value = value.join(&self.compute(*id));
+                    match self.get(id) {
+                        Expr::Constant { value: input, .. } => value = value.meet(input),
+                        _ => return ty.any(),
+                    }
                 }
                 value
             }
-            Expr::Constant { value } => value.clone(),
-            Expr::Binary { lhs_id, op, rhs_id } => {
+            Expr::Constant { value, .. } => value.clone(),
+            Expr::Binary {
+                lhs_id,
+                op,
+                rhs_id,
+                ty,
+            } => {
                 let lhs = self.compute(*lhs_id);
                 let rhs = self.compute(*rhs_id);
-                if let (
-                    &Value::Int(IntValue::Constant(lhs_value)),
-                    &Value::Int(IntValue::Constant(rhs_value)),
-                ) = (&lhs, &rhs)
+                if let (Value::Int(lhs_value), Value::Int(rhs_value), Type::Int(int_ty)) =
+                    (&lhs, &rhs, self.ty(*lhs_id))
                 {
-                    match op {
-                        BinaryOp::Add => Value::Int(IntValue::Constant(lhs_value + rhs_value)),
-                        BinaryOp::Subtract => Value::Int(IntValue::Constant(lhs_value - rhs_value)),
-                        BinaryOp::Multiply => Value::Int(IntValue::Constant(lhs_value * rhs_value)),
-                        BinaryOp::Divide => Value::Int(IntValue::Constant(lhs_value / rhs_value)),
-                        BinaryOp::Modulo => Value::Int(IntValue::Constant(lhs_value % rhs_value)),
-                        BinaryOp::And => Value::Int(IntValue::Constant(lhs_value & rhs_value)),
-                        BinaryOp::Or => Value::Int(IntValue::Constant(lhs_value | rhs_value)),
-                        BinaryOp::Xor => Value::Int(IntValue::Constant(lhs_value ^ rhs_value)),
-                        BinaryOp::ShiftLeft => {
-                            Value::Int(IntValue::Constant(lhs_value << rhs_value))
-                        }
-                        BinaryOp::ShiftRight => {
-                            Value::Int(IntValue::Constant(lhs_value >> rhs_value))
-                        }
-                        BinaryOp::Equal => Value::Bool(BoolValue::Constant(lhs_value == rhs_value)),
-                        BinaryOp::NotEqual => {
-                            Value::Bool(BoolValue::Constant(lhs_value != rhs_value))
-                        }
-                        BinaryOp::LessThan => {
-                            Value::Bool(BoolValue::Constant(lhs_value < rhs_value))
-                        }
-                        BinaryOp::GreaterThan => {
-                            Value::Bool(BoolValue::Constant(lhs_value > rhs_value))
-                        }
-                        BinaryOp::LessThanOrEqual => {
-                            Value::Bool(BoolValue::Constant(lhs_value <= rhs_value))
-                        }
-                        BinaryOp::GreaterThanOrEqual => {
-                            Value::Bool(BoolValue::Constant(lhs_value >= rhs_value))
-                        }
-                        _ => panic!("binary operator '{}' invalid for integers", op),
-                    }
+                    compute_int(*op, int_ty, lhs_value, rhs_value).unwrap_or_else(|| ty.any())
+                } else if let (
+                    Value::Float(lhs_value),
+                    Value::Float(rhs_value),
+                    Type::Float(float_ty),
+                ) = (&lhs, &rhs, self.ty(*lhs_id))
+                {
+                    compute_float(*op, float_ty, lhs_value, rhs_value).unwrap_or_else(|| ty.any())
                 } else if let (
                     &Value::Bool(BoolValue::Constant(lhs_value)),
                     &Value::Bool(BoolValue::Constant(rhs_value)),
@@ -380,28 +795,91 @@ impl Arena<Expr> {
                         BinaryOp::LogicalOr => {
                             Value::Bool(BoolValue::Constant(lhs_value || rhs_value))
                         }
-                        _ => panic!("binary operator '{}' invalid for booleans", op),
+                        BinaryOp::And => Value::Bool(BoolValue::Constant(lhs_value & rhs_value)),
+                        BinaryOp::Or => Value::Bool(BoolValue::Constant(lhs_value | rhs_value)),
+                        BinaryOp::Xor => Value::Bool(BoolValue::Constant(lhs_value ^ rhs_value)),
+                        BinaryOp::Equal => Value::Bool(BoolValue::Constant(lhs_value == rhs_value)),
+                        BinaryOp::NotEqual => {
+                            Value::Bool(BoolValue::Constant(lhs_value != rhs_value))
+                        }
+                        // ill-typed, already reported by the parser
+                        _ => ty.any(),
                     }
                 } else {
-                    lhs.join(&rhs)
+                    ty.any()
                 }
             }
-            Expr::Unary { op, expr_id } => {
+            Expr::Unary { op, expr_id, ty } => {
                 let expr = self.compute(*expr_id);
-                if let Value::Int(IntValue::Constant(expr)) = expr {
+                let value = if let Value::Int(IntValue::Constant(expr)) = expr {
                     match op {
-                        UnaryOp::Negate => Value::Int(IntValue::Constant(-expr)),
-                        UnaryOp::Not => Value::Int(IntValue::Constant(!expr)),
+                        UnaryOp::Negate => Value::Int(IntValue::Constant(expr.wrapping_neg())),
+                        UnaryOp::Complement => Value::Int(IntValue::Constant(!expr)),
+                        // ill-typed, already reported by the parser
+                        UnaryOp::Not => ty.any(),
                     }
                 } else if let Value::Bool(BoolValue::Constant(expr)) = expr {
                     match op {
                         UnaryOp::Not => Value::Bool(BoolValue::Constant(!expr)),
-                        _ => panic!("unary operator '{}' invalid for booleans", op),
+                        // ill-typed, already reported by the parser
+                        _ => ty.any(),
+                    }
+                } else if let (Value::Float(value), UnaryOp::Negate) = (&expr, op) {
+                    Value::Float(value.neg())
+                } else if let Value::Int(value) = expr {
+                    match op {
+                        UnaryOp::Negate => Value::Int(value.neg()),
+                        UnaryOp::Complement => Value::Int(value.complement()),
+                        // ill-typed, already reported by the parser
+                        UnaryOp::Not => ty.any(),
                     }
                 } else {
-                    expr
+                    ty.any()
+                };
+                match (value, ty) {
+                    (Value::Int(value), Type::Int(int_ty)) => Value::Int(int_ty.wrap_value(&value)),
+                    (value, _) => value,
                 }
             }
+            Expr::Convert { expr_id, ty } => {
+                match (self.compute(*expr_id), self.ty(*expr_id), ty) {
+                    (Value::Int(value), _, Type::Int(int_ty)) => {
+                        Value::Int(int_ty.wrap_value(&value))
+                    }
+                    (Value::Bool(BoolValue::Constant(value)), _, Type::Int(_)) => {
+                        Value::Int(IntValue::Constant(value as i64))
+                    }
+                    (Value::Bool(_), _, Type::Int(_)) => Value::Int(IntValue::range(0, 1)),
+                    (Value::Int(value), _, Type::Bool) => {
+                        Value::Bool(value.equal(&IntValue::Constant(0)).not())
+                    }
+                    (Value::Int(IntValue::Constant(value)), Type::Int(from), Type::Float(to)) => {
+                        Value::Float(FloatValue::Constant(to.from_int(from, value)))
+                    }
+                    // integers never turn into NaN
+                    (Value::Int(_), _, Type::Float(_)) => Value::Float(FloatValue::NotNaN),
+                    (Value::Float(FloatValue::Constant(value)), _, Type::Float(to)) => {
+                        Value::Float(FloatValue::Constant(to.round(value)))
+                    }
+                    (Value::Float(value), _, Type::Float(_)) => Value::Float(value),
+                    (Value::Float(FloatValue::Constant(value)), _, Type::Int(to)) => {
+                        Value::Int(IntValue::Constant(to.from_float(value)))
+                    }
+                    _ => ty.any(),
+                }
+            }
+            Expr::Proj { ty, .. } => ty.any(),
+        }
+    }
+
+    pub fn ty(&self, expr_id: Id<Expr>) -> Type {
+        match self.get(&expr_id) {
+            Expr::Phi { ty, .. } => *ty,
+            Expr::Constant { ty, .. } => *ty,
+            Expr::Binary { ty, .. } => *ty,
+            Expr::Unary { ty, .. } => *ty,
+            Expr::Convert { ty, .. } => *ty,
+            Expr::Proj { ty, .. } => *ty,
         }
     }
 
@@ -427,13 +905,619 @@ impl Arena<Expr> {
             Expr::Constant { value } => {
                 add_attr(attributes, "label", &format!("{}", value));
             }
-            Expr::Binary { lhs_id, op, rhs_id } => {
+            Expr::Binary {
+                        lhs_id, op, rhs_id, ..
+                    } => {
                 add_attr(attributes, "label", &format!("\"{}\"", op));
             }
-            Expr::Unary { op, expr_id } => {
+            Expr::Unary { op, expr_id, .. } => {
                 add_attr(attributes, "label", &format!("\"{}\"", op));
             }
         }
     }
     */
 }
+
+// value of a binary operation on integers of type `ty`, None if it can be anything
+fn compute_int(op: BinaryOp, ty: IntType, lhs: &IntValue, rhs: &IntValue) -> Option<Value> {
+    // shift amounts are taken modulo the width of the type
+    let rhs = match op {
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight => {
+            rhs.and(&IntValue::Constant(ty.bits() as i64 - 1))
+        }
+        _ => rhs.clone(),
+    };
+    // u64 values above i64::MAX are negative bit patterns, which only the signed ops below that
+    // work on the bits get right
+    if ty == IntType::U64
+        && matches!(
+            op,
+            BinaryOp::Divide
+                | BinaryOp::Modulo
+                | BinaryOp::ShiftRight
+                | BinaryOp::LessThan
+                | BinaryOp::GreaterThan
+                | BinaryOp::LessThanOrEqual
+                | BinaryOp::GreaterThanOrEqual
+        )
+    {
+        match (lhs, &rhs) {
+            (&IntValue::Constant(lhs_value), &IntValue::Constant(rhs_value)) => {
+                return compute_u64(op, lhs_value as u64, rhs_value as u64);
+            }
+            _ => match (lhs.bounds(), rhs.bounds()) {
+                (Some((lhs_min, _)), Some((rhs_min, _))) if lhs_min >= 0 && rhs_min >= 0 => {}
+                _ => return None,
+            },
+        }
+    }
+    let value = if let (&IntValue::Constant(lhs_value), &IntValue::Constant(rhs_value)) =
+        (lhs, &rhs)
+    {
+        match op {
+            BinaryOp::Add => Value::Int(IntValue::Constant(lhs_value.wrapping_add(rhs_value))),
+            BinaryOp::Subtract => Value::Int(IntValue::Constant(lhs_value.wrapping_sub(rhs_value))),
+            BinaryOp::Multiply => Value::Int(IntValue::Constant(lhs_value.wrapping_mul(rhs_value))),
+            // division by zero is left for run time
+            BinaryOp::Divide => match lhs_value.checked_div(rhs_value) {
+                Some(value) => Value::Int(IntValue::Constant(value)),
+                None if rhs_value == -1 => Value::Int(IntValue::Constant(lhs_value)),
+                None => return None,
+            },
+            BinaryOp::Modulo => match lhs_value.checked_rem(rhs_value) {
+                Some(value) => Value::Int(IntValue::Constant(value)),
+                None if rhs_value == -1 => Value::Int(IntValue::Constant(0)),
+                None => return None,
+            },
+            BinaryOp::And => Value::Int(IntValue::Constant(lhs_value & rhs_value)),
+            BinaryOp::Or => Value::Int(IntValue::Constant(lhs_value | rhs_value)),
+            BinaryOp::Xor => Value::Int(IntValue::Constant(lhs_value ^ rhs_value)),
+            BinaryOp::ShiftLeft => {
+                Value::Int(IntValue::Constant(lhs_value.wrapping_shl(rhs_value as u32)))
+            }
+            BinaryOp::ShiftRight => {
+                Value::Int(IntValue::Constant(lhs_value.wrapping_shr(rhs_value as u32)))
+            }
+            BinaryOp::Equal => Value::Bool(BoolValue::Constant(lhs_value == rhs_value)),
+            BinaryOp::NotEqual => Value::Bool(BoolValue::Constant(lhs_value != rhs_value)),
+            BinaryOp::LessThan => Value::Bool(BoolValue::Constant(lhs_value < rhs_value)),
+            BinaryOp::GreaterThan => Value::Bool(BoolValue::Constant(lhs_value > rhs_value)),
+            BinaryOp::LessThanOrEqual => Value::Bool(BoolValue::Constant(lhs_value <= rhs_value)),
+            BinaryOp::GreaterThanOrEqual => {
+                Value::Bool(BoolValue::Constant(lhs_value >= rhs_value))
+            }
+            // ill-typed, already reported by the parser
+            _ => return None,
+        }
+    } else {
+        // not both constant, work with ranges
+        match op {
+            BinaryOp::Add => Value::Int(lhs.add(&rhs)),
+            BinaryOp::Subtract => Value::Int(lhs.sub(&rhs)),
+            BinaryOp::Multiply => Value::Int(lhs.mul(&rhs)),
+            BinaryOp::Divide => Value::Int(lhs.div(&rhs)),
+            BinaryOp::Modulo => Value::Int(lhs.rem(&rhs)),
+            BinaryOp::And => Value::Int(lhs.and(&rhs)),
+            BinaryOp::Or => Value::Int(lhs.or(&rhs)),
+            BinaryOp::Xor => Value::Int(lhs.xor(&rhs)),
+            BinaryOp::ShiftLeft => Value::Int(lhs.shl(&rhs)),
+            BinaryOp::ShiftRight => Value::Int(lhs.shr(&rhs)),
+            BinaryOp::Equal => Value::Bool(lhs.equal(&rhs)),
+            BinaryOp::NotEqual => Value::Bool(lhs.equal(&rhs).not()),
+            BinaryOp::LessThan => Value::Bool(lhs.less_than(&rhs)),
+            BinaryOp::GreaterThan => Value::Bool(rhs.less_than(lhs)),
+            BinaryOp::LessThanOrEqual => Value::Bool(rhs.less_than(lhs).not()),
+            BinaryOp::GreaterThanOrEqual => Value::Bool(lhs.less_than(&rhs).not()),
+            // ill-typed, already reported by the parser
+            _ => return None,
+        }
+    };
+    // results wrap around to the type
+    match value {
+        Value::Int(value) => Some(Value::Int(ty.wrap_value(&value))),
+        value => Some(value),
+    }
+}
+
+// the u64 operations that differ from their i64 counterparts, on constants
+fn compute_u64(op: BinaryOp, lhs_value: u64, rhs_value: u64) -> Option<Value> {
+    match op {
+        // division by zero is left for run time
+        BinaryOp::Divide => Some(Value::Int(IntValue::Constant(
+            lhs_value.checked_div(rhs_value)? as i64,
+        ))),
+        BinaryOp::Modulo => Some(Value::Int(IntValue::Constant(
+            lhs_value.checked_rem(rhs_value)? as i64,
+        ))),
+        BinaryOp::ShiftRight => Some(Value::Int(IntValue::Constant(
+            (lhs_value >> rhs_value) as i64,
+        ))),
+        BinaryOp::LessThan => Some(Value::Bool(BoolValue::Constant(lhs_value < rhs_value))),
+        BinaryOp::GreaterThan => Some(Value::Bool(BoolValue::Constant(lhs_value > rhs_value))),
+        BinaryOp::LessThanOrEqual => Some(Value::Bool(BoolValue::Constant(lhs_value <= rhs_value))),
+        BinaryOp::GreaterThanOrEqual => {
+            Some(Value::Bool(BoolValue::Constant(lhs_value >= rhs_value)))
+        }
+        _ => None,
+    }
+}
+
+// value of a binary operation on floats of type `ty`, None if it can be anything
+fn compute_float(op: BinaryOp, ty: FloatType, lhs: &FloatValue, rhs: &FloatValue) -> Option<Value> {
+    if let (&FloatValue::Constant(lhs_value), &FloatValue::Constant(rhs_value)) = (lhs, rhs) {
+        // f64 is wide enough that doing an f32 operation in f64 and rounding gives the same
+        // result
+        let value = match op {
+            BinaryOp::Add => lhs_value + rhs_value,
+            BinaryOp::Subtract => lhs_value - rhs_value,
+            BinaryOp::Multiply => lhs_value * rhs_value,
+            BinaryOp::Divide => lhs_value / rhs_value,
+            BinaryOp::Modulo => lhs_value % rhs_value,
+            BinaryOp::Equal => {
+                return Some(Value::Bool(BoolValue::Constant(lhs_value == rhs_value)))
+            }
+            BinaryOp::NotEqual => {
+                return Some(Value::Bool(BoolValue::Constant(lhs_value != rhs_value)))
+            }
+            BinaryOp::LessThan => {
+                return Some(Value::Bool(BoolValue::Constant(lhs_value < rhs_value)))
+            }
+            BinaryOp::GreaterThan => {
+                return Some(Value::Bool(BoolValue::Constant(lhs_value > rhs_value)))
+            }
+            BinaryOp::LessThanOrEqual => {
+                return Some(Value::Bool(BoolValue::Constant(lhs_value <= rhs_value)))
+            }
+            BinaryOp::GreaterThanOrEqual => {
+                return Some(Value::Bool(BoolValue::Constant(lhs_value >= rhs_value)))
+            }
+            // ill-typed, already reported by the parser
+            _ => return None,
+        };
+        return Some(Value::Float(FloatValue::Constant(ty.round(value))));
+    }
+    // not both constant, only NaN-ness is known
+    match op {
+        BinaryOp::Add => Some(Value::Float(lhs.add(rhs))),
+        BinaryOp::Subtract => Some(Value::Float(lhs.add(&rhs.neg()))),
+        BinaryOp::Multiply => Some(Value::Float(lhs.mul(rhs))),
+        BinaryOp::Divide => Some(Value::Float(lhs.div(rhs))),
+        BinaryOp::NotEqual => Some(Value::Bool(lhs.compare(rhs).not())),
+        BinaryOp::Equal
+        | BinaryOp::LessThan
+        | BinaryOp::GreaterThan
+        | BinaryOp::LessThanOrEqual
+        | BinaryOp::GreaterThanOrEqual => Some(Value::Bool(lhs.compare(rhs))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    const I64: Type = Type::Int(IntType::I64);
+    const U64: Type = Type::Int(IntType::U64);
+    const F64: Type = Type::Float(FloatType::F64);
+
+    // expressions over the arguments of a function, each peepholed as the parser does
+    struct Graph {
+        exprs: Arena<Expr>,
+        start_id: Id<Ctrl>,
+        args: usize,
+    }
+
+    impl Graph {
+        fn new() -> Self {
+            let mut ctrls = Arena::new();
+            let start_id = ctrls.alloc(Ctrl::Start {
+                arg_ids: Vec::new(),
+                symbols: Symbols::new(),
+            });
+            Self {
+                exprs: Arena::new(),
+                start_id,
+                args: 0,
+            }
+        }
+
+        fn arg(&mut self, ty: Type) -> Id<Expr> {
+            let index = self.args;
+            self.args += 1;
+            self.exprs.alloc(Expr::Proj {
+                ctrl_id: self.start_id,
+                index,
+                ty,
+            })
+        }
+
+        fn constant(&mut self, value: Value, ty: Type) -> Id<Expr> {
+            self.exprs.alloc(Expr::Constant { value, ty })
+        }
+
+        fn int(&mut self, value: i64, ty: Type) -> Id<Expr> {
+            self.constant(Value::Int(IntValue::Constant(value)), ty)
+        }
+
+        fn float(&mut self, value: f64) -> Id<Expr> {
+            self.constant(Value::Float(FloatValue::Constant(value)), F64)
+        }
+
+        fn bool(&mut self, value: bool) -> Id<Expr> {
+            self.constant(Value::Bool(BoolValue::Constant(value)), Type::Bool)
+        }
+
+        fn binary(&mut self, lhs_id: Id<Expr>, op: BinaryOp, rhs_id: Id<Expr>) -> Id<Expr> {
+            let ty = op.ty(self.exprs.ty(lhs_id), self.exprs.ty(rhs_id)).unwrap();
+            let id = self.exprs.alloc(Expr::Binary {
+                lhs_id,
+                op,
+                rhs_id,
+                ty,
+            });
+            self.exprs.peephole(id)
+        }
+
+        fn unary(&mut self, op: UnaryOp, expr_id: Id<Expr>) -> Id<Expr> {
+            let ty = op.ty(self.exprs.ty(expr_id)).unwrap();
+            let id = self.exprs.alloc(Expr::Unary { op, expr_id, ty });
+            self.exprs.peephole(id)
+        }
+
+        fn convert(&mut self, expr_id: Id<Expr>, ty: Type) -> Id<Expr> {
+            let id = self.exprs.alloc(Expr::Convert { expr_id, ty });
+            self.exprs.peephole(id)
+        }
+
+        // the value of a constant, None if the expression wasn't folded
+        fn value(&self, id: Id<Expr>) -> Option<Value> {
+            match self.exprs.get(&id) {
+                Expr::Constant { value, .. } => Some(value.clone()),
+                _ => None,
+            }
+        }
+    }
+
+    // whether `id` converts `expr_id` straight to `ty`
+    fn converts(graph: &Graph, id: Id<Expr>, expr_id: Id<Expr>, ty: Type) -> bool {
+        match graph.exprs.get(&id) {
+            Expr::Convert {
+                expr_id: inner_id,
+                ty: inner_ty,
+            } => *inner_id == expr_id && *inner_ty == ty,
+            _ => false,
+        }
+    }
+
+    fn bool(value: bool) -> Option<Value> {
+        Some(Value::Bool(BoolValue::Constant(value)))
+    }
+
+    fn int(value: i64) -> Option<Value> {
+        Some(Value::Int(IntValue::Constant(value)))
+    }
+
+    #[test]
+    fn boolean_operators() {
+        let mut graph = Graph::new();
+        let b = graph.arg(Type::Bool);
+        let (t, f) = (graph.bool(true), graph.bool(false));
+        assert_eq!(graph.binary(f, BinaryOp::LogicalAnd, b), f);
+        assert_eq!(graph.binary(t, BinaryOp::LogicalAnd, b), b);
+        assert_eq!(graph.binary(b, BinaryOp::LogicalAnd, t), b);
+        assert_eq!(graph.binary(b, BinaryOp::LogicalOr, f), b);
+        assert_eq!(graph.binary(t, BinaryOp::LogicalOr, b), t);
+        assert_eq!(graph.binary(b, BinaryOp::Or, t), t);
+        assert_eq!(graph.binary(b, BinaryOp::Xor, f), b);
+        let xor = graph.binary(b, BinaryOp::Xor, b);
+        assert_eq!(graph.value(xor), bool(false));
+    }
+
+    #[test]
+    fn same_operand_comparisons() {
+        let mut graph = Graph::new();
+        for ty in [I64, U64] {
+            let x = graph.arg(ty);
+            let equal = graph.binary(x, BinaryOp::Equal, x);
+            assert_eq!(graph.value(equal), bool(true));
+            let not_equal = graph.binary(x, BinaryOp::NotEqual, x);
+            assert_eq!(graph.value(not_equal), bool(false));
+            let less = graph.binary(x, BinaryOp::LessThan, x);
+            assert_eq!(graph.value(less), bool(false));
+            let greater_or_equal = graph.binary(x, BinaryOp::GreaterThanOrEqual, x);
+            assert_eq!(graph.value(greater_or_equal), bool(true));
+        }
+    }
+
+    #[test]
+    fn double_unary() {
+        let mut graph = Graph::new();
+        for ty in [I64, F64] {
+            let x = graph.arg(ty);
+            let negated = graph.unary(UnaryOp::Negate, x);
+            assert_eq!(graph.unary(UnaryOp::Negate, negated), x);
+        }
+        for ty in [I64, U64] {
+            let x = graph.arg(ty);
+            let complemented = graph.unary(UnaryOp::Complement, x);
+            assert_eq!(graph.unary(UnaryOp::Complement, complemented), x);
+        }
+        let b = graph.arg(Type::Bool);
+        let not = graph.unary(UnaryOp::Not, b);
+        assert_eq!(graph.unary(UnaryOp::Not, not), b);
+        // a different operator in between stays
+        let x = graph.arg(I64);
+        let complemented = graph.unary(UnaryOp::Complement, x);
+        let negated = graph.unary(UnaryOp::Negate, complemented);
+        assert!(matches!(graph.exprs.get(&negated), Expr::Unary { .. }));
+    }
+
+    #[test]
+    fn inverted_comparisons() {
+        let mut graph = Graph::new();
+        for ty in [I64, U64] {
+            let (a, b) = (graph.arg(ty), graph.arg(ty));
+            let less = graph.binary(a, BinaryOp::LessThan, b);
+            let not = graph.unary(UnaryOp::Not, less);
+            assert!(matches!(
+                graph.exprs.get(&not),
+                Expr::Binary { lhs_id, op: BinaryOp::GreaterThanOrEqual, rhs_id, .. }
+                    if *lhs_id == a && *rhs_id == b
+            ));
+        }
+        // both a < b and a >= b are false for NaN
+        let (a, b) = (graph.arg(F64), graph.arg(F64));
+        let less = graph.binary(a, BinaryOp::LessThan, b);
+        let not = graph.unary(UnaryOp::Not, less);
+        assert!(matches!(
+            graph.exprs.get(&not),
+            Expr::Unary {
+                op: UnaryOp::Not,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn range_comparisons() {
+        let mut graph = Graph::new();
+        // signed
+        let x = graph.arg(I64);
+        let ten = graph.int(10, I64);
+        let rem = graph.binary(x, BinaryOp::Modulo, ten);
+        let less = graph.binary(rem, BinaryOp::LessThan, ten);
+        assert_eq!(graph.value(less), bool(true));
+        let x = graph.arg(Type::Int(IntType::I8));
+        let x = graph.convert(x, I64);
+        let min = graph.int(-128, I64);
+        let less = graph.binary(x, BinaryOp::LessThan, min);
+        assert_eq!(graph.value(less), bool(false));
+        let greater = graph.binary(x, BinaryOp::GreaterThan, min);
+        assert_eq!(graph.value(greater), None);
+
+        // unsigned, where u64 values with the top bit set must not be taken as negative
+        let x = graph.arg(Type::Int(IntType::U8));
+        let x = graph.convert(x, U64);
+        let max = graph.int(255, U64);
+        let less_or_equal = graph.binary(x, BinaryOp::LessThanOrEqual, max);
+        assert_eq!(graph.value(less_or_equal), bool(true));
+        let x = graph.arg(U64);
+        let one = graph.int(1, U64);
+        let less = graph.binary(x, BinaryOp::LessThan, one);
+        assert_eq!(graph.value(less), None);
+
+        // comparisons with NaN are false
+        let x = graph.arg(F64);
+        let nan = graph.float(f64::NAN);
+        let less = graph.binary(nan, BinaryOp::LessThan, x);
+        assert_eq!(graph.value(less), bool(false));
+        let one = graph.float(1.0);
+        let less = graph.binary(x, BinaryOp::LessThan, one);
+        assert_eq!(graph.value(less), None);
+    }
+
+    #[test]
+    fn bitwise_canonical_form() {
+        let mut graph = Graph::new();
+        for ty in [I64, U64] {
+            let x = graph.arg(ty);
+            let five = graph.int(5, ty);
+            for op in [BinaryOp::And, BinaryOp::Or, BinaryOp::Xor] {
+                let id = graph.binary(five, op, x);
+                assert!(matches!(
+                    graph.exprs.get(&id),
+                    Expr::Binary { lhs_id, rhs_id, .. } if *lhs_id == x && *rhs_id == five
+                ));
+            }
+            assert_eq!(graph.binary(x, BinaryOp::And, x), x);
+            assert_eq!(graph.binary(x, BinaryOp::Or, x), x);
+            let xor = graph.binary(x, BinaryOp::Xor, x);
+            assert_eq!(graph.value(xor), int(0));
+            let zero = graph.int(0, ty);
+            assert_eq!(graph.binary(x, BinaryOp::Xor, zero), x);
+        }
+    }
+
+    #[test]
+    fn bitwise_known_bits() {
+        let mut graph = Graph::new();
+        // signed
+        let x = graph.arg(I64);
+        let (high, byte) = (graph.int(0xf0, I64), graph.int(0xff, I64));
+        let masked = graph.binary(x, BinaryOp::And, high);
+        assert_eq!(graph.binary(masked, BinaryOp::And, byte), masked);
+        let one = graph.int(1, I64);
+        let odd = graph.binary(x, BinaryOp::Or, one);
+        assert_eq!(graph.binary(odd, BinaryOp::Or, one), odd);
+        let zero = graph.int(0, I64);
+        let equal = graph.binary(odd, BinaryOp::Equal, zero);
+        assert_eq!(graph.value(equal), bool(false));
+        let (four, low) = (graph.int(4, I64), graph.int(15, I64));
+        let shifted = graph.binary(x, BinaryOp::ShiftLeft, four);
+        let masked = graph.binary(shifted, BinaryOp::And, low);
+        assert_eq!(graph.value(masked), int(0));
+        // the sign bits of a negative i8 survive the mask
+        let x = graph.arg(Type::Int(IntType::I8));
+        let x = graph.convert(x, I64);
+        let masked = graph.binary(x, BinaryOp::And, byte);
+        assert_ne!(masked, x);
+
+        // unsigned
+        let x = graph.arg(Type::Int(IntType::U8));
+        let x = graph.convert(x, U64);
+        let byte = graph.int(0xff, U64);
+        assert_eq!(graph.binary(x, BinaryOp::And, byte), x);
+        let bit = graph.int(0x100, U64);
+        let masked = graph.binary(x, BinaryOp::And, bit);
+        assert_eq!(graph.value(masked), int(0));
+    }
+
+    #[test]
+    fn shifts() {
+        let mut graph = Graph::new();
+        // by a multiple of the width
+        for (ty, width) in [(IntType::I32, 32), (IntType::U8, 8), (IntType::U64, 64)] {
+            let x = graph.arg(Type::Int(ty));
+            let width = graph.int(width, Type::Int(ty));
+            assert_eq!(graph.binary(x, BinaryOp::ShiftLeft, width), x);
+            assert_eq!(graph.binary(x, BinaryOp::ShiftRight, width), x);
+            let three = graph.int(3, Type::Int(ty));
+            assert_ne!(graph.binary(x, BinaryOp::ShiftLeft, three), x);
+        }
+
+        // of 0 and -1
+        let y = graph.arg(I64);
+        let (zero, minus_one) = (graph.int(0, I64), graph.int(-1, I64));
+        let shifted = graph.binary(zero, BinaryOp::ShiftLeft, y);
+        assert_eq!(graph.value(shifted), int(0));
+        let shifted = graph.binary(minus_one, BinaryOp::ShiftRight, y);
+        assert_eq!(graph.value(shifted), int(-1));
+        let y = graph.arg(U64);
+        let (zero, max) = (graph.int(0, U64), graph.int(-1, U64));
+        let shifted = graph.binary(zero, BinaryOp::ShiftRight, y);
+        assert_eq!(graph.value(shifted), int(0));
+        // u64::MAX >> y is logical, so it isn't u64::MAX
+        let shifted = graph.binary(max, BinaryOp::ShiftRight, y);
+        assert_eq!(graph.value(shifted), None);
+    }
+
+    #[test]
+    fn wrapping() {
+        let mut graph = Graph::new();
+        let (i8, u8) = (Type::Int(IntType::I8), Type::Int(IntType::U8));
+        let (max, one) = (graph.int(127, i8), graph.int(1, i8));
+        let sum = graph.binary(max, BinaryOp::Add, one);
+        assert_eq!(graph.value(sum), int(-128));
+        let (max, one) = (graph.int(255, u8), graph.int(1, u8));
+        let sum = graph.binary(max, BinaryOp::Add, one);
+        assert_eq!(graph.value(sum), int(0));
+        let (max, one) = (graph.int(-1, U64), graph.int(1, U64));
+        let quotient = graph.binary(max, BinaryOp::Divide, one);
+        assert_eq!(graph.value(quotient), int(-1));
+        let two = graph.int(2, U64);
+        let quotient = graph.binary(max, BinaryOp::Divide, two);
+        assert_eq!(graph.value(quotient), int(i64::MAX));
+    }
+
+    #[test]
+    fn float_identities() {
+        let mut graph = Graph::new();
+        let x = graph.arg(F64);
+        let (one, zero, negative_zero) = (graph.float(1.0), graph.float(0.0), graph.float(-0.0));
+        assert_eq!(graph.binary(x, BinaryOp::Multiply, one), x);
+        assert_eq!(graph.binary(one, BinaryOp::Multiply, x), x);
+        assert_eq!(graph.binary(x, BinaryOp::Divide, one), x);
+        assert_eq!(graph.binary(x, BinaryOp::Add, negative_zero), x);
+        assert_eq!(graph.binary(negative_zero, BinaryOp::Add, x), x);
+        assert_eq!(graph.binary(x, BinaryOp::Subtract, zero), x);
+        // -0.0 + 0.0 is 0.0 and -0.0 - -0.0 is 0.0
+        assert_ne!(graph.binary(x, BinaryOp::Add, zero), x);
+        assert_ne!(graph.binary(x, BinaryOp::Subtract, negative_zero), x);
+        // inf - inf is NaN
+        let difference = graph.binary(x, BinaryOp::Subtract, x);
+        assert_eq!(graph.value(difference), None);
+
+        for ty in [I64, U64] {
+            let x = graph.arg(ty);
+            let one = graph.int(1, ty);
+            assert_eq!(graph.binary(one, BinaryOp::Multiply, x), x);
+            assert_eq!(graph.binary(x, BinaryOp::Divide, one), x);
+            let difference = graph.binary(x, BinaryOp::Subtract, x);
+            assert_eq!(graph.value(difference), int(0));
+        }
+    }
+
+    #[test]
+    fn float_same_operand_comparisons() {
+        let mut graph = Graph::new();
+        // x may be NaN
+        let x = graph.arg(F64);
+        for op in [
+            BinaryOp::Equal,
+            BinaryOp::NotEqual,
+            BinaryOp::LessThanOrEqual,
+            BinaryOp::GreaterThanOrEqual,
+        ] {
+            let id = graph.binary(x, op, x);
+            assert_eq!(graph.value(id), None);
+        }
+        let less = graph.binary(x, BinaryOp::LessThan, x);
+        assert_eq!(graph.value(less), bool(false));
+
+        // an integer converted to a float isn't NaN
+        for ty in [I64, U64] {
+            let i = graph.arg(ty);
+            let x = graph.convert(i, F64);
+            let equal = graph.binary(x, BinaryOp::Equal, x);
+            assert_eq!(graph.value(equal), bool(true));
+            let less_or_equal = graph.binary(x, BinaryOp::LessThanOrEqual, x);
+            assert_eq!(graph.value(less_or_equal), bool(true));
+        }
+    }
+
+    #[test]
+    fn conversion_chains() {
+        let mut graph = Graph::new();
+        let (i8, i16, i32) = (
+            Type::Int(IntType::I8),
+            Type::Int(IntType::I16),
+            Type::Int(IntType::I32),
+        );
+        // signed
+        let x = graph.arg(i32);
+        let wide = graph.convert(x, I64);
+        assert_eq!(graph.convert(wide, i32), x);
+        let narrow = graph.convert(x, i16);
+        let id = graph.convert(narrow, i8);
+        assert!(converts(&graph, id, x, i8));
+        // the high bits are lost on the way
+        let x = graph.arg(I64);
+        let narrow = graph.convert(x, i8);
+        let id = graph.convert(narrow, I64);
+        assert!(converts(&graph, id, narrow, I64));
+
+        // unsigned
+        let x = graph.arg(Type::Int(IntType::U8));
+        let wide = graph.convert(x, Type::Int(IntType::U32));
+        let id = graph.convert(wide, U64);
+        assert!(converts(&graph, id, x, U64));
+        let b = graph.arg(Type::Bool);
+        let byte = graph.convert(b, Type::Int(IntType::U8));
+        let id = graph.convert(byte, I64);
+        assert!(converts(&graph, id, b, I64));
+
+        // float
+        let f32 = Type::Float(FloatType::F32);
+        let x = graph.arg(f32);
+        let wide = graph.convert(x, F64);
+        assert_eq!(graph.convert(wide, f32), x);
+        let x = graph.arg(F64);
+        let narrow = graph.convert(x, f32);
+        let id = graph.convert(narrow, F64);
+        assert!(converts(&graph, id, narrow, F64));
+        let x = graph.arg(i32);
+        let wide = graph.convert(x, I64);
+        let id = graph.convert(wide, F64);
+        assert!(converts(&graph, id, x, F64));
+    }
+}
@@ -0,0 +1,11 @@
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub span: Span,
+    pub params: Vec<(String, Type)>,
+    pub ty: Type,
+    pub start_id: Id<Ctrl>,
+    pub stop_id: Id<Ctrl>,
+}
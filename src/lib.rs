@@ -1,6 +1,15 @@
 mod visualize;
 pub use visualize::*;
 
+mod span;
+pub use span::*;
+
+mod diagnostic;
+pub use diagnostic::*;
+
+mod renderer;
+pub use renderer::*;
+
 mod token;
 pub use token::*;
 
@@ -16,6 +25,9 @@ pub use symbols::*;
 mod parser;
 pub use parser::*;
 
+mod types;
+pub use types::*;
+
 mod value;
 pub use value::*;
 
@@ -30,3 +42,6 @@ pub use expr::*;
 
 mod ctrl;
 pub use ctrl::*;
+
+mod function;
+pub use function::*;
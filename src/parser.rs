@@ -1,9 +1,42 @@
-use crate::*;
+use {crate::*, std::collections::HashMap};
+
+// a point in the source to return to
+struct Position<'a> {
+    tokenizer: Tokenizer<'a>,
+    current: Option<Token>,
+    span: Span,
+    last: Span,
+}
+
+// the paths leaving a loop through break and continue
+struct LoopScope {
+    label: Option<String>,
+    depth: usize,
+    break_ids: Vec<Id<Ctrl>>,
+    continue_ids: Vec<Id<Ctrl>>,
+}
+
+// fn name(type name, ...) -> type
+struct Signature {
+    name: String,
+    span: Span,
+    params: Vec<(String, Type)>,
+    ty: Type,
+}
 
 pub struct Parser<'a> {
     tokenizer: Tokenizer<'a>,
     current: Option<Token>,
+    span: Span,
+    last: Span,
     pi: usize,
+    diagnostics: Vec<Diagnostic>,
+    return_ids: Vec<Id<Ctrl>>,
+    return_ty: Type,
+    functions: Vec<Function>,
+    signatures: HashMap<String, (Vec<Type>, Type)>,
+    call_ids: Vec<Id<Ctrl>>,
+    loops: Vec<LoopScope>,
     exprs: Arena<Expr>,
     ctrls: Arena<Ctrl>,
 }
@@ -11,11 +44,24 @@ pub struct Parser<'a> {
 impl<'a> Parser<'a> {
     pub fn new(source: &'a str) -> Self {
         let mut tokenizer = Tokenizer::new(source);
-        let current = tokenizer.next();
+        let (current, span) = match tokenizer.next() {
+            Some((token, span)) => (Some(token), span),
+            None => (None, tokenizer.position()),
+        };
+        let diagnostics = tokenizer.take_diagnostics();
         Self {
             tokenizer,
             current,
+            span,
+            last: span,
             pi: 0,
+            diagnostics,
+            return_ids: Vec::new(),
+            return_ty: Type::Int(IntType::I64),
+            functions: Vec::new(),
+            signatures: HashMap::new(),
+            call_ids: Vec::new(),
+            loops: Vec::new(),
             exprs: Arena::new(),
             ctrls: Arena::new(),
         }
@@ -29,77 +75,398 @@ impl<'a> Parser<'a> {
     }
 
     fn consume(&mut self) {
-        self.current = self.tokenizer.next();
+        self.last = self.span;
+        let next = self.tokenizer.next();
+        // tokens lexed again after a rewind report their errors only once
+        for diagnostic in self.tokenizer.take_diagnostics() {
+            if !self.diagnostics.iter().any(|reported| {
+                reported.span == diagnostic.span && reported.message == diagnostic.message
+            }) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+        match next {
+            Some((token, span)) => {
+                self.current = Some(token);
+                self.span = span;
+            }
+            None => {
+                self.current = None;
+                self.span = self.tokenizer.position();
+            }
+        }
     }
 
-    fn expect(&mut self, token: Token) {
+    fn error(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(message, self.span)
+    }
+
+    // span from the start of `start` to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            offset: start.offset,
+            length: (self.last.offset + self.last.length).saturating_sub(start.offset),
+            line: start.line,
+            column: start.column,
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), Diagnostic> {
         if let Some(t) = &self.current {
             if *t == token {
                 self.consume();
+                Ok(())
+            } else {
+                Err(self.error(format!("expected `{}`, got `{}`", token, t)))
+            }
+        } else {
+            Err(self.error(format!("expected `{}`, got end of source", token)))
+        }
+    }
+
+    fn position(&self) -> Position<'a> {
+        Position {
+            tokenizer: self.tokenizer.clone(),
+            current: self.current.clone(),
+            span: self.span,
+            last: self.last,
+        }
+    }
+
+    // continue parsing from an earlier position
+    fn rewind(&mut self, position: Position<'a>) {
+        self.tokenizer = position.tokenizer;
+        self.current = position.current;
+        self.span = position.span;
+        self.last = position.last;
+    }
+
+    // skip to the end of the current statement, so parsing can continue after an error; nested
+    // blocks are skipped whole, and the `}` closing the current block is left for the caller
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.current {
+                Some(Token::Semicolon) if depth == 0 => {
+                    self.consume();
+                    break;
+                }
+                Some(Token::OpenBrace) => {
+                    depth += 1;
+                    self.consume();
+                }
+                Some(Token::CloseBrace) if depth == 0 => break,
+                Some(Token::CloseBrace) => {
+                    depth -= 1;
+                    self.consume();
+                    // a statement ending in a block, unless an `else` follows
+                    if depth == 0 && self.current != Some(Token::Else) {
+                        break;
+                    }
+                }
+                None => break,
+                _ => self.consume(),
+            }
+        }
+    }
+
+    fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn parse_program(&mut self) -> Result<Vec<Function>, Vec<Diagnostic>> {
+        self.declare_functions();
+
+        while let Some(token) = &self.current {
+            let result = if *token == Token::Fn {
+                self.parse_function()
             } else {
-                panic!("expected `{}`, got `{}`", token, t);
+                Err(self.error(format!("program: expected `fn`, got `{}`", token)))
+            };
+            if let Err(diagnostic) = result {
+                self.diagnostics.push(diagnostic);
+                // skip to the next function
+                self.consume();
+                while !matches!(self.current, Some(Token::Fn) | None) {
+                    self.consume();
+                }
             }
+        }
+
+        // now that all functions are known, point every call at its callee
+        for call_id in self.call_ids.iter() {
+            let start_id = match self.ctrls.get(call_id) {
+                Ctrl::Call { name, .. } => self
+                    .functions
+                    .iter()
+                    .find(|function| function.name == *name)
+                    .map(|function| function.start_id),
+                _ => None,
+            };
+            if let Some(start_id) = start_id {
+                self.ctrls.add_input(*call_id, Input::Ctrl(start_id));
+            }
+        }
+
+        self.diagnostics
+            .sort_by_key(|diagnostic| diagnostic.span.offset);
+        if self
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            Err(self.diagnostics.clone())
         } else {
-            panic!("expected `{}`, got end of source", token);
+            Ok(self.functions.clone())
         }
     }
 
-    pub fn parse_program(&mut self) -> Id<Ctrl> {
-        let ctrl_id: Id<Ctrl> = self.ctrls.alloc(Ctrl::Start {
+    // collect the signatures of all functions up front, so calls can be type checked before the
+    // callee is parsed
+    fn declare_functions(&mut self) {
+        let start = self.position();
+        let count = self.diagnostics.len();
+        while self.current.is_some() {
+            if self.current != Some(Token::Fn) {
+                self.consume();
+                continue;
+            }
+            if let Ok(signature) = self.parse_signature() {
+                let param_tys = signature.params.iter().map(|(_, ty)| *ty).collect();
+                self.signatures
+                    .entry(signature.name)
+                    .or_insert((param_tys, signature.ty));
+            }
+            // skip the body
+            let mut depth = 0;
+            while let Some(token) = &self.current {
+                match token {
+                    Token::Fn if depth == 0 => break,
+                    Token::OpenBrace => depth += 1,
+                    Token::CloseBrace if depth <= 1 => {
+                        self.consume();
+                        break;
+                    }
+                    Token::CloseBrace => depth -= 1,
+                    _ => {}
+                }
+                self.consume();
+            }
+        }
+        // reported again when the functions are parsed
+        self.diagnostics.truncate(count);
+        self.rewind(start);
+    }
+
+    // fn name(type name, ...) -> type { body }
+    fn parse_function(&mut self) -> Result<(), Diagnostic> {
+        let Signature {
+            name,
+            span,
+            params,
+            ty,
+        } = self.parse_signature()?;
+        if let Some(function) = self.functions.iter().find(|function| function.name == name) {
+            let diagnostic = Diagnostic::error(
+                format!("function `{}` is defined more than once", name),
+                span,
+            )
+            .with_label(function.span, "previous definition here");
+            self.diagnostics.push(diagnostic);
+        }
+
+        // the parameters are projections out of Start, bound in the root scope
+        let start_id = self.ctrls.alloc(Ctrl::Start {
             arg_ids: Vec::new(),
             symbols: Symbols::new(),
         });
-
-        self.ctrls.symbols_mut(ctrl_id).push_scope();
-
-        // TODO: add args to scope as Proj
-        let mut result: Option<Id<Ctrl>> = None;
-        while let Some(_) = self.current {
-            result = self.parse_statement(ctrl_id);
+        self.ctrls.symbols_mut(start_id).push_scope();
+        let mut arg_ids = Vec::new();
+        for (index, (param, ty)) in params.iter().enumerate() {
+            let arg_id = self.exprs.alloc(Expr::Proj {
+                ctrl_id: start_id,
+                index,
+                ty: *ty,
+            });
+            self.ctrls.symbols_mut(start_id).declare(param, arg_id);
+            arg_ids.push(arg_id);
         }
+        self.ctrls.set_arg_ids(start_id, arg_ids);
 
+        self.return_ids.clear();
+        self.return_ty = ty;
+        if self.current != Some(Token::OpenBrace) {
+            return Err(self.error("function: `{` expected"));
+        }
+        let errors = self.error_count();
+        let ctrl_id = self.parse_statement(start_id)?;
         self.ctrls.symbols_mut(ctrl_id).pop_scope();
+        // don't pile onto errors that may have swallowed a return
+        if !self.ctrls.is_dead(ctrl_id) && self.error_count() == errors {
+            let message = if self.return_ids.is_empty() {
+                format!("function `{}`: return statement expected", name)
+            } else {
+                format!("function `{}`: missing return on some path", name)
+            };
+            self.diagnostics.push(
+                Diagnostic::error(message, span)
+                    .with_label(self.last, "control reaches the end of the function here"),
+            );
+        }
+
+        // all returns end up in Stop
+        let stop_id = self.ctrls.alloc(Ctrl::Stop {
+            ctrl_ids: std::mem::take(&mut self.return_ids),
+        });
+
+        self.functions.push(Function {
+            name,
+            span,
+            params,
+            ty,
+            start_id,
+            stop_id,
+        });
+        Ok(())
+    }
 
-        if let Some(result) = result {
-            result
+    // fn name(type name, ...) -> type
+    fn parse_signature(&mut self) -> Result<Signature, Diagnostic> {
+        self.consume(); // fn
+        let span = self.span;
+        let name = if let Some(Token::Identifier(name)) = &self.current {
+            name.clone()
         } else {
-            panic!("program: return statement expected");
+            return Err(self.error("function: identifier expected"));
+        };
+        self.consume(); // name
+        self.expect(Token::OpenParen)?;
+        let mut params: Vec<(String, Type)> = Vec::new();
+        while self.current != Some(Token::CloseParen) {
+            if !params.is_empty() {
+                self.expect(Token::Comma)?;
+            }
+            let ty = self.parse_type()?;
+            let param = if let Some(Token::Identifier(param)) = &self.current {
+                param.clone()
+            } else {
+                return Err(self.error("function: parameter name expected"));
+            };
+            if params.iter().any(|(other, _)| *other == param) {
+                let diagnostic =
+                    self.error(format!("parameter `{}` is declared more than once", param));
+                self.diagnostics.push(diagnostic);
+            }
+            self.consume(); // parameter name
+            params.push((param, ty));
         }
+        self.consume(); // )
+        self.expect(Token::MinusGreater)?;
+        let ty = self.parse_type()?;
+        Ok(Signature {
+            name,
+            span,
+            params,
+            ty,
+        })
     }
 
-    fn parse_statement(&mut self, ctrl_id: Id<Ctrl>) -> Option<Id<Ctrl>> {
+    // whether the current identifier is followed by `(`
+    fn at_call(&self) -> bool {
+        matches!(self.tokenizer.clone().next(), Some((Token::OpenParen, _)))
+    }
+
+    fn at_type(&self) -> bool {
+        matches!(
+            self.current,
+            Some(
+                Token::Int
+                    | Token::I8
+                    | Token::I16
+                    | Token::I32
+                    | Token::I64
+                    | Token::U8
+                    | Token::U16
+                    | Token::U32
+                    | Token::U64
+                    | Token::F32
+                    | Token::F64
+                    | Token::Bool
+            )
+        )
+    }
+
+    fn parse_type(&mut self) -> Result<Type, Diagnostic> {
+        let ty = match self.current {
+            Some(Token::Int | Token::I64) => Type::Int(IntType::I64),
+            Some(Token::I8) => Type::Int(IntType::I8),
+            Some(Token::I16) => Type::Int(IntType::I16),
+            Some(Token::I32) => Type::Int(IntType::I32),
+            Some(Token::U8) => Type::Int(IntType::U8),
+            Some(Token::U16) => Type::Int(IntType::U16),
+            Some(Token::U32) => Type::Int(IntType::U32),
+            Some(Token::U64) => Type::Int(IntType::U64),
+            Some(Token::F32) => Type::Float(FloatType::F32),
+            Some(Token::F64) => Type::Float(FloatType::F64),
+            Some(Token::Bool) => Type::Bool,
+            _ => return Err(self.error("type expected")),
+        };
+        self.consume();
+        Ok(ty)
+    }
+
+    // parse a statement starting at control `ctrl_id`, returns the control after the statement
+    fn parse_statement(&mut self, mut ctrl_id: Id<Ctrl>) -> Result<Id<Ctrl>, Diagnostic> {
         //#[allow(unused_assignments)]
         //let mut title = String::new();
-        let result_id: Option<Id<Ctrl>> = match &self.current {
+        let next_id: Id<Ctrl> = match &self.current {
             Some(Token::Return) => {
                 self.consume();
-                let expr_id = self.parse_expression(ctrl_id);
+                let start = self.span;
+                let expr_id = self.parse_expression(&mut ctrl_id)?;
                 let expr_id = self.exprs.peephole(expr_id);
+                let expr_id = self.check(start, self.return_ty, expr_id);
                 //title = format!("return {};", expr_id);
-                let result_id = self.ctrls.alloc(Ctrl::Return { ctrl_id, expr_id });
-                self.expect(Token::Semicolon);
-                Some(result_id)
+                self.expect(Token::Semicolon)?;
+                // continue parsing on a dead path, keeping the scopes for the enclosing blocks
+                let symbols = self.ctrls.symbols(ctrl_id).clone();
+                if !self.ctrls.is_dead(ctrl_id) {
+                    let return_id = self.ctrls.alloc(Ctrl::Return { ctrl_id, expr_id });
+                    self.return_ids.push(return_id);
+                }
+                self.ctrls.alloc(Ctrl::Dead { symbols })
             }
-            Some(Token::Int) => {
-                self.consume();
+            _ if self.at_type() => {
+                let ty = self.parse_type()?;
                 let name = if let Some(Token::Identifier(name)) = &self.current {
                     name.clone()
                 } else {
-                    panic!("declaration statement: identifier expected");
+                    return Err(self.error("declaration statement: identifier expected"));
                 };
                 self.consume(); // name
-                self.expect(Token::Equal);
-                let expr_id = self.parse_expression(ctrl_id);
+                self.expect(Token::Equal)?;
+                let start = self.span;
+                let expr_id = self.parse_expression(&mut ctrl_id)?;
                 let expr_id = self.exprs.peephole(expr_id);
+                let expr_id = self.check(start, ty, expr_id);
                 //title = format!("int {} = {};", name, expr_id);
-                self.expect(Token::Semicolon);
+                self.expect(Token::Semicolon)?;
                 self.ctrls.symbols_mut(ctrl_id).declare(&name, expr_id);
-                None
+                ctrl_id
             }
             Some(Token::OpenBrace) => {
                 self.consume();
+                let mut ctrl_id = ctrl_id;
                 self.ctrls.symbols_mut(ctrl_id).push_scope();
-                let mut result_id: Option<Id<Ctrl>> = None;
+                // the statement after which control doesn't continue, until reported
+                let mut exit: Option<(Option<Token>, Span)> = None;
                 loop {
                     match self.current {
                         Some(Token::CloseBrace) => {
@@ -107,454 +474,2052 @@ impl<'a> Parser<'a> {
                             break;
                         }
                         None => {
-                            panic!("block statement: unexpected end of source");
+                            self.ctrls.symbols_mut(ctrl_id).pop_scope();
+                            return Err(self.error("block statement: unexpected end of source"));
                         }
                         _ => {
-                            result_id = self.parse_statement(ctrl_id);
+                            let token = self.current.clone();
+                            let start = self.span;
+                            let dead = self.ctrls.is_dead(ctrl_id);
+                            match self.parse_statement(ctrl_id) {
+                                Ok(next_id) => ctrl_id = next_id,
+                                Err(diagnostic) => {
+                                    self.diagnostics.push(diagnostic);
+                                    self.synchronize();
+                                }
+                            }
+                            if let Some((token, span)) = exit.take() {
+                                let message = match token {
+                                    Some(Token::Return) => "unreachable code after `return`",
+                                    Some(Token::Break) => "unreachable code after `break`",
+                                    Some(Token::Continue) => "unreachable code after `continue`",
+                                    _ => "unreachable code",
+                                };
+                                self.diagnostics.push(
+                                    Diagnostic::warning(message, self.span_from(start)).with_label(
+                                        span,
+                                        "any code following this statement is unreachable",
+                                    ),
+                                );
+                            } else if !dead && self.ctrls.is_dead(ctrl_id) {
+                                exit = Some((token, self.span_from(start)));
+                            }
                         }
                     }
                 }
                 self.ctrls.symbols_mut(ctrl_id).pop_scope();
                 //title = "{}".to_string();
-                result_id
+                ctrl_id
             }
             Some(Token::If) => {
                 self.consume(); // if
-                let expr_id = self.parse_expression(ctrl_id);
+                let start = self.span;
+                let expr_id = self.parse_expression(&mut ctrl_id)?;
                 let expr_id = self.exprs.peephole(expr_id);
-                let symbols = self.ctrls.symbols(ctrl_id).clone();
-                let then_id = self.ctrls.alloc(Ctrl::Then { ctrl_id, symbols });
-                self.parse_statement(then_id);
+                let expr_id = self.check(start, Type::Bool, expr_id);
+                let (then_id, else_id) = self.branch(ctrl_id, expr_id);
+                // each branch gets its own scope for declarations
+                self.ctrls.symbols_mut(then_id).push_scope();
+                let then_id = self.parse_statement(then_id)?;
+                self.ctrls.symbols_mut(then_id).pop_scope();
                 let else_id = if let Some(Token::Else) = self.current {
                     self.consume(); // else
-                    let symbols = self.ctrls.symbols(ctrl_id).clone();
-                    let else_id = self.ctrls.alloc(Ctrl::Else { ctrl_id, symbols });
-                    self.parse_statement(else_id);
-                    Some(else_id)
+                    self.ctrls.symbols_mut(else_id).push_scope();
+                    let else_id = self.parse_statement(else_id)?;
+                    self.ctrls.symbols_mut(else_id).pop_scope();
+                    else_id
                 } else {
-                    None
+                    else_id
                 };
                 //title = format!("if ({}) {{}}", expr_id);
-                let result_id = self.ctrls.alloc(Ctrl::If {
-                    ctrl_id,
-                    expr_id,
-                    then_id,
-                    else_id,
-                });
-                Some(result_id)
+                self.merge(vec![then_id, else_id])
             }
-            Some(Token::Identifier(name)) => {
-                let name = name.clone();
-                self.consume(); // identifier
-                self.expect(Token::Equal);
-                let expr_id = self.parse_expression(ctrl_id);
+            Some(Token::While) => self.parse_while(ctrl_id, None)?,
+            Some(Token::For) => self.parse_for(ctrl_id, None)?,
+            Some(Token::Do) => self.parse_do_while(ctrl_id, None)?,
+            Some(Token::Label(label)) => {
+                let label = label.clone();
+                self.consume(); // label
+                self.expect(Token::Colon)?;
+                match self.current {
+                    Some(Token::While) => self.parse_while(ctrl_id, Some(label))?,
+                    Some(Token::For) => self.parse_for(ctrl_id, Some(label))?,
+                    Some(Token::Do) => self.parse_do_while(ctrl_id, Some(label))?,
+                    _ => return Err(self.error("labeled statement: loop expected")),
+                }
+            }
+            Some(Token::Break | Token::Continue) => {
+                let is_break = self.current == Some(Token::Break);
+                let keyword = if is_break { "break" } else { "continue" };
+                let span = self.span;
+                self.consume(); // break or continue
+                let index = if let Some(Token::Label(label)) = &self.current {
+                    let label = label.clone();
+                    let index = self
+                        .loops
+                        .iter()
+                        .rposition(|scope| scope.label.as_ref() == Some(&label));
+                    if index.is_none() {
+                        return Err(self.error(format!("undeclared label `'{}`", label)));
+                    }
+                    self.consume(); // label
+                    index
+                } else {
+                    self.loops.len().checked_sub(1)
+                };
+                let index = match index {
+                    Some(index) => index,
+                    None => {
+                        return Err(Diagnostic::error(
+                            format!("`{}` outside of a loop", keyword),
+                            span,
+                        ))
+                    }
+                };
+                self.expect(Token::Semicolon)?;
+                // continue parsing on a dead path, keeping the scopes for the enclosing blocks
+                let symbols = self.ctrls.symbols(ctrl_id).clone();
+                if !self.ctrls.is_dead(ctrl_id) {
+                    let depth = self.loops[index].depth;
+                    self.ctrls.symbols_mut(ctrl_id).truncate(depth);
+                    if is_break {
+                        self.loops[index].break_ids.push(ctrl_id);
+                    } else {
+                        self.loops[index].continue_ids.push(ctrl_id);
+                    }
+                }
+                self.ctrls.alloc(Ctrl::Dead { symbols })
+            }
+            // a call for its side effects, the result is dropped
+            Some(Token::Identifier(_)) if self.at_call() => {
+                self.parse_expression(&mut ctrl_id)?;
+                self.expect(Token::Semicolon)?;
+                ctrl_id
+            }
+            Some(Token::Identifier(_) | Token::PlusPlus | Token::MinusMinus) => {
+                self.parse_assignment(&mut ctrl_id)?;
+                self.expect(Token::Semicolon)?;
+                ctrl_id
+            }
+            Some(token) => return Err(self.error(format!("statement: unexpected `{}`", token))),
+            None => return Err(self.error("statement: unexpected end of source")),
+        };
+        //visualize(&next, &title, Path::new(&format!("test{}.svg", self.pi))).unwrap();
+        self.pi += 1;
+        Ok(next_id)
+    }
+
+    // assignment, compound assignment, increment or decrement, without the semicolon
+    fn parse_assignment(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<(), Diagnostic> {
+        let name = match &self.current {
+            Some(Token::Identifier(name)) => name.clone(),
+            Some(Token::PlusPlus | Token::MinusMinus) => {
+                self.parse_expression(ctrl_id)?;
+                return Ok(());
+            }
+            _ => return Err(self.error("assignment expected")),
+        };
+        let span = self.span;
+        self.consume(); // identifier
+        if self.ctrls.symbols(*ctrl_id).get(&name).is_none() {
+            return Err(Diagnostic::error(
+                format!("undefined identifier `{}`", name),
+                span,
+            ));
+        }
+        if let Some(Token::PlusPlus | Token::MinusMinus) = self.current {
+            let op = if let Some(Token::PlusPlus) = self.current {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Subtract
+            };
+            self.consume(); // ++ or --
+            self.increment(*ctrl_id, span, &name, op);
+            return Ok(());
+        }
+        let op = match self.current {
+            Some(Token::PlusEqual) => Some(BinaryOp::Add),
+            Some(Token::MinusEqual) => Some(BinaryOp::Subtract),
+            Some(Token::StarEqual) => Some(BinaryOp::Multiply),
+            Some(Token::SlashEqual) => Some(BinaryOp::Divide),
+            Some(Token::PercentEqual) => Some(BinaryOp::Modulo),
+            Some(Token::AmpEqual) => Some(BinaryOp::And),
+            Some(Token::BarEqual) => Some(BinaryOp::Or),
+            Some(Token::CaretEqual) => Some(BinaryOp::Xor),
+            Some(Token::LessLessEqual) => Some(BinaryOp::ShiftLeft),
+            Some(Token::GreaterGreaterEqual) => Some(BinaryOp::ShiftRight),
+            _ => None,
+        };
+        if op.is_some() {
+            self.consume();
+        } else {
+            self.expect(Token::Equal)?;
+        }
+        // a op= b -> a = a op b, with a read before b can change it
+        let lhs_id = self.lookup(*ctrl_id, &name).unwrap();
+        let ty = self.exprs.ty(lhs_id);
+        let expr_id = self.parse_expression(ctrl_id)?;
+        let expr_id = self.exprs.peephole(expr_id);
+        let expr_id = if let Some(op) = op {
+            let errors = self.diagnostics.len();
+            let expr_id = self.binary(span, lhs_id, op, expr_id);
+            if self.diagnostics.len() > errors {
+                // already reported by binary
+                self.exprs.alloc(Expr::Constant {
+                    value: ty.any(),
+                    ty,
+                })
+            } else {
                 let expr_id = self.exprs.peephole(expr_id);
-                //title = format!("{} = {};", name, expr_id);
-                self.expect(Token::Semicolon);
-                self.ctrls.symbols_mut(ctrl_id).set(&name, expr_id);
-                None
+                self.check(span, ty, expr_id)
             }
-            Some(token) => panic!("statement: unexpected `{}`", token),
-            None => panic!("statement: unexpected end of source"),
+        } else {
+            self.check(span, ty, expr_id)
+        };
+        self.ctrls.symbols_mut(*ctrl_id).set(&name, expr_id);
+        Ok(())
+    }
+
+    // split control on a condition, each branch gets its own copy of the scopes
+    // a branch on a constant condition is never taken the other way
+    fn branch(&mut self, ctrl_id: Id<Ctrl>, expr_id: Id<Expr>) -> (Id<Ctrl>, Id<Ctrl>) {
+        let if_id = self.ctrls.alloc(Ctrl::If { ctrl_id, expr_id });
+        let condition = match self.exprs.get(&expr_id) {
+            Expr::Constant {
+                value: Value::Bool(BoolValue::Constant(condition)),
+                ..
+            } => Some(*condition),
+            _ => None,
         };
-        if let Some(_result_id) = &result_id {
-            //let result_id = Rc::clone(&result_id);
-            //visualize(&result, &title, Path::new(&format!("test{}.svg", self.pi))).unwrap();
+        let symbols = self.ctrls.symbols(ctrl_id).clone();
+        let then_id = if condition == Some(false) {
+            self.ctrls.alloc(Ctrl::Dead { symbols })
         } else {
-            //visualize(&ctrl, &title, Path::new(&format!("test{}.svg", self.pi))).unwrap();
+            self.ctrls.alloc(Ctrl::Then {
+                ctrl_id: if_id,
+                symbols,
+            })
+        };
+        let symbols = self.ctrls.symbols(ctrl_id).clone();
+        let else_id = if condition == Some(true) {
+            self.ctrls.alloc(Ctrl::Dead { symbols })
+        } else {
+            self.ctrls.alloc(Ctrl::Else {
+                ctrl_id: if_id,
+                symbols,
+            })
+        };
+        (then_id, else_id)
+    }
+
+    fn parse_condition(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_expression(ctrl_id)?;
+        let expr_id = self.exprs.peephole(expr_id);
+        Ok(self.check(start, Type::Bool, expr_id))
+    }
+
+    // start a loop at control `ctrl_id`, every variable gets a phi when it is first used
+    fn open_loop(&mut self, ctrl_id: Id<Ctrl>) -> Id<Ctrl> {
+        let mut symbols = self.ctrls.symbols(ctrl_id).clone();
+        let loop_id = self.ctrls.alloc(Ctrl::Loop {
+            ctrl_id,
+            back_id: None,
+            phis: Symbols::new(),
+            symbols: Symbols::new(),
+        });
+        symbols.make_lazy(loop_id);
+        *self.ctrls.phis_mut(loop_id) = symbols.clone();
+        *self.ctrls.symbols_mut(loop_id) = symbols;
+        loop_id
+    }
+
+    // parse a loop body starting at control `body_id`, returns the end of the body and the
+    // break and continue paths
+    fn parse_loop_body(
+        &mut self,
+        loop_id: Id<Ctrl>,
+        body_id: Id<Ctrl>,
+        label: Option<String>,
+    ) -> Result<(Id<Ctrl>, LoopScope), Diagnostic> {
+        self.loops.push(LoopScope {
+            label,
+            depth: self.ctrls.phis(loop_id).depth(),
+            break_ids: Vec::new(),
+            continue_ids: Vec::new(),
+        });
+        self.ctrls.symbols_mut(body_id).push_scope();
+        let body_id = self.parse_statement(body_id);
+        let scope = self.loops.pop().unwrap();
+        let body_id = body_id?;
+        self.ctrls.symbols_mut(body_id).pop_scope();
+        Ok((body_id, scope))
+    }
+
+    // while (condition) body
+    fn parse_while(
+        &mut self,
+        ctrl_id: Id<Ctrl>,
+        label: Option<String>,
+    ) -> Result<Id<Ctrl>, Diagnostic> {
+        self.consume(); // while
+        let loop_id = self.open_loop(ctrl_id);
+        let mut ctrl_id = loop_id;
+        let expr_id = self.parse_condition(&mut ctrl_id)?;
+        let (body_id, exit_id) = self.branch(ctrl_id, expr_id);
+        let (body_id, scope) = self.parse_loop_body(loop_id, body_id, label)?;
+        let mut back_ids = vec![body_id];
+        back_ids.extend(scope.continue_ids);
+        let back_id = self.merge(back_ids);
+        self.close_loop(loop_id, back_id);
+        let mut exit_ids = vec![exit_id];
+        exit_ids.extend(scope.break_ids);
+        Ok(self.merge(exit_ids))
+    }
+
+    // for (init; condition; step) body
+    fn parse_for(
+        &mut self,
+        ctrl_id: Id<Ctrl>,
+        label: Option<String>,
+    ) -> Result<Id<Ctrl>, Diagnostic> {
+        self.consume(); // for
+        self.expect(Token::OpenParen)?;
+        let start = self.position();
+        self.skip_parenthesized();
+        let header_end = self.span.offset;
+        self.rewind(start);
+        // the init variable is scoped to the loop, also when the loop fails to parse
+        self.ctrls.symbols_mut(ctrl_id).push_scope();
+        match self.parse_for_loop(ctrl_id, label) {
+            Ok(exit_id) => {
+                self.ctrls.symbols_mut(exit_id).pop_scope();
+                Ok(exit_id)
+            }
+            Err(diagnostic) => {
+                self.ctrls.symbols_mut(ctrl_id).pop_scope();
+                // skip the rest of the header, so recovery doesn't stop at a `;` inside it
+                if self.current.is_some() && self.span.offset < header_end {
+                    while self.current.is_some() && self.span.offset < header_end {
+                        self.consume();
+                    }
+                    self.consume(); // )
+                }
+                Err(diagnostic)
+            }
         }
-        self.pi += 1;
-        if let Some(result_id) = result_id {
-            Some(result_id)
+    }
+
+    // skip to the `)` closing the parentheses we are in
+    fn skip_parenthesized(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.current {
+                Some(Token::OpenParen) => depth += 1,
+                Some(Token::CloseParen) if depth == 0 => break,
+                Some(Token::CloseParen) => depth -= 1,
+                None => break,
+                _ => {}
+            }
+            self.consume();
+        }
+    }
+
+    // the rest of a for loop after `(`, inside the scope of the init variable
+    fn parse_for_loop(
+        &mut self,
+        ctrl_id: Id<Ctrl>,
+        label: Option<String>,
+    ) -> Result<Id<Ctrl>, Diagnostic> {
+        let ctrl_id = match self.current {
+            Some(Token::Semicolon) => {
+                self.consume();
+                ctrl_id
+            }
+            Some(Token::Identifier(_) | Token::PlusPlus | Token::MinusMinus) => {
+                self.parse_statement(ctrl_id)?
+            }
+            _ if self.at_type() => self.parse_statement(ctrl_id)?,
+            _ => return Err(self.error("for statement: declaration or assignment expected")),
+        };
+        let loop_id = self.open_loop(ctrl_id);
+        let mut ctrl_id = loop_id;
+        let expr_id = if let Some(Token::Semicolon) = self.current {
+            self.exprs.alloc(Expr::Constant {
+                value: Value::Bool(BoolValue::Constant(true)),
+                ty: Type::Bool,
+            })
+        } else {
+            self.parse_condition(&mut ctrl_id)?
+        };
+        self.expect(Token::Semicolon)?;
+        let (body_id, exit_id) = self.branch(ctrl_id, expr_id);
+
+        // the step runs after the body, so skip it for now and come back to it later
+        let step = self.position();
+        self.skip_parenthesized();
+        self.expect(Token::CloseParen)?;
+
+        let (body_id, scope) = self.parse_loop_body(loop_id, body_id, label)?;
+        let mut back_ids = vec![body_id];
+        back_ids.extend(scope.continue_ids);
+        let mut back_id = self.merge(back_ids);
+        let after = self.position();
+        self.rewind(step);
+        let result = if let Some(Token::CloseParen) = self.current {
+            Ok(())
+        } else {
+            self.parse_assignment(&mut back_id)
+                .and_then(|_| self.expect(Token::CloseParen))
+        };
+        self.rewind(after);
+        if let Err(diagnostic) = result {
+            self.diagnostics.push(diagnostic);
+        }
+        self.close_loop(loop_id, back_id);
+        let mut exit_ids = vec![exit_id];
+        exit_ids.extend(scope.break_ids);
+        Ok(self.merge(exit_ids))
+    }
+
+    // do body while (condition);
+    fn parse_do_while(
+        &mut self,
+        ctrl_id: Id<Ctrl>,
+        label: Option<String>,
+    ) -> Result<Id<Ctrl>, Diagnostic> {
+        self.consume(); // do
+        let loop_id = self.open_loop(ctrl_id);
+        let (body_id, scope) = self.parse_loop_body(loop_id, loop_id, label)?;
+        // continue jumps to the condition
+        let mut ids = vec![body_id];
+        ids.extend(scope.continue_ids);
+        let mut ctrl_id = self.merge(ids);
+        self.expect(Token::While)?;
+        let expr_id = self.parse_condition(&mut ctrl_id)?;
+        self.expect(Token::Semicolon)?;
+        let (back_id, exit_id) = self.branch(ctrl_id, expr_id);
+        self.close_loop(loop_id, back_id);
+        let mut exit_ids = vec![exit_id];
+        exit_ids.extend(scope.break_ids);
+        Ok(self.merge(exit_ids))
+    }
+
+    // join control paths, with a phi for every variable that is bound differently along them
+    fn merge(&mut self, ctrl_ids: Vec<Id<Ctrl>>) -> Id<Ctrl> {
+        let live_ids: Vec<Id<Ctrl>> = ctrl_ids
+            .iter()
+            .copied()
+            .filter(|ctrl_id| !self.ctrls.is_dead(*ctrl_id))
+            .collect();
+        match live_ids.len() {
+            0 => return ctrl_ids[0],
+            1 => return live_ids[0],
+            _ => {}
+        }
+        let ctrl_ids = live_ids;
+        let mut symbols = self.ctrls.symbols(ctrl_ids[0]).clone();
+        let merge_id = self.ctrls.alloc(Ctrl::Merge {
+            ctrl_ids: ctrl_ids.clone(),
+            symbols: Symbols::new(),
+        });
+        for (depth, name) in symbols.names() {
+            let bindings: Vec<Binding> = ctrl_ids
+                .iter()
+                .map(|ctrl_id| self.ctrls.symbols(*ctrl_id).get_at(depth, &name).unwrap())
+                .collect();
+            if bindings.iter().all(|binding| *binding == bindings[0]) {
+                continue;
+            }
+            let expr_ids: Vec<Id<Expr>> = ctrl_ids
+                .iter()
+                .map(|ctrl_id| self.resolve(*ctrl_id, depth, &name))
+                .collect();
+            if expr_ids.iter().all(|expr_id| *expr_id == expr_ids[0]) {
+                symbols.set_at(depth, &name, Binding::Expr(expr_ids[0]));
+                continue;
+            }
+            let ty = self.exprs.ty(expr_ids[0]);
+            let phi_id = self.exprs.alloc(Expr::Phi {
+                ctrl: merge_id,
+                expr_ids,
+                ty,
+            });
+            let phi_id = self.exprs.peephole(phi_id);
+            symbols.set_at(depth, &name, Binding::Expr(phi_id));
+        }
+        *self.ctrls.symbols_mut(merge_id) = symbols;
+        merge_id
+    }
+
+    // join control paths that each produce a value, returns the value after the join
+    fn join(&mut self, ctrl_id: &mut Id<Ctrl>, paths: Vec<(Id<Ctrl>, Id<Expr>)>) -> Id<Expr> {
+        let expr_ids: Vec<Id<Expr>> = paths
+            .iter()
+            .filter(|(ctrl_id, _)| !self.ctrls.is_dead(*ctrl_id))
+            .map(|(_, expr_id)| *expr_id)
+            .collect();
+        *ctrl_id = self.merge(paths.iter().map(|(ctrl_id, _)| *ctrl_id).collect());
+        match expr_ids.len() {
+            0 => paths[0].1,
+            1 => expr_ids[0],
+            _ => {
+                let ty = self.exprs.ty(expr_ids[0]);
+                let phi_id = self.exprs.alloc(Expr::Phi {
+                    ctrl: *ctrl_id,
+                    expr_ids,
+                    ty,
+                });
+                self.exprs.peephole(phi_id)
+            }
+        }
+    }
+
+    // the current value of a variable
+    fn lookup(&mut self, ctrl_id: Id<Ctrl>, name: &str) -> Option<Id<Expr>> {
+        let (depth, _) = self.ctrls.symbols(ctrl_id).get(name)?;
+        Some(self.resolve(ctrl_id, depth, name))
+    }
+
+    // the value of a variable at a scope depth, creating the loop phi if it was still lazy
+    fn resolve(&mut self, ctrl_id: Id<Ctrl>, depth: usize, name: &str) -> Id<Expr> {
+        match self.ctrls.symbols(ctrl_id).get_at(depth, name).unwrap() {
+            Binding::Expr(expr_id) => expr_id,
+            Binding::Lazy(loop_id) => {
+                let expr_id = self.loop_phi(loop_id, depth, name);
+                self.ctrls
+                    .symbols_mut(ctrl_id)
+                    .set_at(depth, name, Binding::Expr(expr_id));
+                expr_id
+            }
+        }
+    }
+
+    // the value of a variable at the top of a loop
+    fn loop_phi(&mut self, loop_id: Id<Ctrl>, depth: usize, name: &str) -> Id<Expr> {
+        let (entry_id, back_id) = match self.ctrls.get(&loop_id) {
+            Ctrl::Loop {
+                ctrl_id, back_id, ..
+            } => (*ctrl_id, *back_id),
+            _ => panic!("lazy binding to a control that is not a loop"),
+        };
+        if let Some(Binding::Expr(expr_id)) = self.ctrls.phis(loop_id).get_at(depth, name) {
+            return expr_id;
+        }
+        let entry_expr_id = self.resolve(entry_id, depth, name);
+        if back_id.is_some() {
+            // loop is closed and the variable never changed inside it
+            return entry_expr_id;
+        }
+        let ty = self.exprs.ty(entry_expr_id);
+        let phi_id = self.exprs.alloc(Expr::Phi {
+            ctrl: loop_id,
+            expr_ids: vec![entry_expr_id],
+            ty,
+        });
+        self.ctrls
+            .phis_mut(loop_id)
+            .set_at(depth, name, Binding::Expr(phi_id));
+        phi_id
+    }
+
+    // add the back edge to a loop, completing its phis and removing the ones that turn out to
+    // be loop-invariant
+    fn close_loop(&mut self, loop_id: Id<Ctrl>, back_id: Id<Ctrl>) {
+        let entry_id = match self.ctrls.get(&loop_id) {
+            Ctrl::Loop { ctrl_id, .. } => *ctrl_id,
+            _ => panic!("closing a control that is not a loop"),
+        };
+        self.ctrls.add_input(loop_id, Input::Ctrl(back_id));
+        // a loop body that never gets back to the top changes nothing
+        let dead = self.ctrls.is_dead(back_id);
+        for (depth, name) in self.ctrls.phis(loop_id).names() {
+            let back_binding = if dead {
+                Binding::Lazy(loop_id)
+            } else {
+                self.ctrls.symbols(back_id).get_at(depth, &name).unwrap()
+            };
+            if back_binding == Binding::Lazy(loop_id) {
+                // unchanged inside the loop
+                if let Some(Binding::Expr(phi_id)) = self.ctrls.phis(loop_id).get_at(depth, &name) {
+                    self.complete_phi(phi_id, phi_id);
+                }
+                continue;
+            }
+            let back_expr_id = self.resolve(back_id, depth, &name);
+            match self.ctrls.phis(loop_id).get_at(depth, &name).unwrap() {
+                Binding::Expr(phi_id) => self.complete_phi(phi_id, back_expr_id),
+                Binding::Lazy(_) => {
+                    // changed inside the loop, but never read there
+                    let entry_expr_id = self.resolve(entry_id, depth, &name);
+                    if entry_expr_id == back_expr_id {
+                        continue;
+                    }
+                    let ty = self.exprs.ty(entry_expr_id);
+                    let phi_id = self.exprs.alloc(Expr::Phi {
+                        ctrl: loop_id,
+                        expr_ids: vec![entry_expr_id, back_expr_id],
+                        ty,
+                    });
+                    let phi_id = self.exprs.peephole(phi_id);
+                    self.ctrls
+                        .phis_mut(loop_id)
+                        .set_at(depth, &name, Binding::Expr(phi_id));
+                }
+            }
+        }
+    }
+
+    // add the back edge value to a loop phi, and replace the phi if it turns out to be constant
+    // or invariant
+    fn complete_phi(&mut self, phi_id: Id<Expr>, back_expr_id: Id<Expr>) {
+        let entry_expr_id = match self.exprs.get(&phi_id) {
+            Expr::Phi { expr_ids, .. } => expr_ids[0],
+            _ => return,
+        };
+        self.exprs.add_input(phi_id, Input::Expr(back_expr_id));
+        let new_id = if back_expr_id == phi_id {
+            entry_expr_id
+        } else {
+            self.exprs.peephole(phi_id)
+        };
+        if new_id != phi_id {
+            self.exprs.replace(Input::Expr(phi_id), Input::Expr(new_id));
+            self.ctrls.replace_expr(phi_id, new_id);
+            debug_assert!(self.ctrls.users(Input::Expr(phi_id)).is_empty());
+            self.exprs.kill(phi_id);
+        }
+    }
+
+    // x = x + 1 or x = x - 1, returns the old and the new value of x
+    fn increment(
+        &mut self,
+        ctrl_id: Id<Ctrl>,
+        start: Span,
+        name: &str,
+        op: BinaryOp,
+    ) -> (Id<Expr>, Id<Expr>) {
+        let old_id = self.lookup(ctrl_id, name).unwrap();
+        let one_id = self.exprs.alloc(Expr::Constant {
+            value: Value::Int(IntValue::Constant(1)),
+            ty: Type::Int(IntType::I64),
+        });
+        let ty = self.exprs.ty(old_id);
+        let errors = self.diagnostics.len();
+        let new_id = self.binary(start, old_id, op, one_id);
+        let new_id = if self.diagnostics.len() > errors {
+            // already reported by binary
+            self.exprs.alloc(Expr::Constant {
+                value: ty.any(),
+                ty,
+            })
+        } else {
+            let new_id = self.exprs.peephole(new_id);
+            self.check(start, ty, new_id)
+        };
+        self.ctrls.symbols_mut(ctrl_id).set(name, new_id);
+        (old_id, new_id)
+    }
+
+    // allocate a binary expression, reporting operands the operator can't be applied to
+    fn binary(
+        &mut self,
+        start: Span,
+        lhs_id: Id<Expr>,
+        op: BinaryOp,
+        rhs_id: Id<Expr>,
+    ) -> Id<Expr> {
+        let (lhs_id, rhs_id) = match op {
+            // the shift amount keeps its own type
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => (lhs_id, rhs_id),
+            _ => self.unify(lhs_id, rhs_id),
+        };
+        let lhs_ty = self.exprs.ty(lhs_id);
+        let rhs_ty = self.exprs.ty(rhs_id);
+        let ty = match op.ty(lhs_ty, rhs_ty) {
+            Some(ty) => ty,
+            None if self.reported(start) => op.fallback_ty(lhs_ty),
+            None => {
+                self.diagnostics.push(Diagnostic::error(
+                    format!(
+                        "binary operator `{}` cannot be applied to `{}` and `{}`",
+                        op, lhs_ty, rhs_ty
+                    ),
+                    self.span_from(start),
+                ));
+                op.fallback_ty(lhs_ty)
+            }
+        };
+        self.exprs.alloc(Expr::Binary {
+            lhs_id,
+            op,
+            rhs_id,
+            ty,
+        })
+    }
+
+    // bring numeric operands of different types to a common type, a constant operand takes the
+    // type of the other side if it fits
+    fn unify(&mut self, lhs_id: Id<Expr>, rhs_id: Id<Expr>) -> (Id<Expr>, Id<Expr>) {
+        let lhs_ty = self.exprs.ty(lhs_id);
+        let rhs_ty = self.exprs.ty(rhs_id);
+        if lhs_ty == rhs_ty {
+            return (lhs_id, rhs_id);
+        }
+        let ty = if self.holds(lhs_ty, rhs_id) {
+            lhs_ty
+        } else if self.holds(rhs_ty, lhs_id) {
+            rhs_ty
+        } else if let Some(ty) = lhs_ty.common(rhs_ty) {
+            ty
         } else {
-            None
+            // reported by the caller
+            return (lhs_id, rhs_id);
+        };
+        (self.convert(lhs_id, ty), self.convert(rhs_id, ty))
+    }
+
+    // whether expression `expr_id` is a constant that keeps its value in type `ty`, float
+    // constants only have to be in range
+    fn holds(&self, ty: Type, expr_id: Id<Expr>) -> bool {
+        match (self.exprs.ty(expr_id), self.exprs.compute(expr_id), ty) {
+            (Type::Int(from), Value::Int(IntValue::Constant(value)), Type::Int(to)) => {
+                to.holds(from, value)
+            }
+            (Type::Int(from), Value::Int(IntValue::Constant(value)), Type::Float(to)) => {
+                to.holds_int(from, value)
+            }
+            (Type::Float(_), Value::Float(FloatValue::Constant(value)), Type::Float(to)) => {
+                to.holds(value)
+            }
+            _ => false,
+        }
+    }
+
+    fn convert(&mut self, expr_id: Id<Expr>, ty: Type) -> Id<Expr> {
+        if self.exprs.ty(expr_id) == ty {
+            return expr_id;
+        }
+        let expr_id = self.exprs.alloc(Expr::Convert { expr_id, ty });
+        self.exprs.peephole(expr_id)
+    }
+
+    // allocate a unary expression, reporting an operand the operator can't be applied to
+    fn unary(&mut self, start: Span, op: UnaryOp, expr_id: Id<Expr>) -> Id<Expr> {
+        let expr_ty = self.exprs.ty(expr_id);
+        let ty = match op.ty(expr_ty) {
+            Some(ty) => ty,
+            None if self.reported(start) => op.fallback_ty(),
+            None => {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("unary operator `{}` cannot be applied to `{}`", op, expr_ty),
+                    self.span_from(start),
+                ));
+                op.fallback_ty()
+            }
+        };
+        self.exprs.alloc(Expr::Unary { op, expr_id, ty })
+    }
+
+    // report an expression that doesn't have the expected type, and continue with an unknown
+    // value of the expected type instead
+    fn check(&mut self, start: Span, ty: Type, expr_id: Id<Expr>) -> Id<Expr> {
+        let expr_ty = self.exprs.ty(expr_id);
+        if expr_ty == ty {
+            return expr_id;
+        }
+        // numbers widen implicitly, constants convert to any numeric type that holds them
+        if expr_ty.widens_to(ty) || self.holds(ty, expr_id) {
+            return self.convert(expr_id, ty);
+        }
+        if !self.reported(start) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("mismatched types: expected `{}`, found `{}`", ty, expr_ty),
+                self.span_from(start),
+            ));
+        }
+        self.exprs.alloc(Expr::Constant {
+            value: ty.any(),
+            ty,
+        })
+    }
+
+    // whether an error was already reported inside the expression starting at `start`, whose
+    // type is then only a guess that isn't worth another error
+    fn reported(&self, start: Span) -> bool {
+        let span = self.span_from(start);
+        self.diagnostics.iter().any(|diagnostic| {
+            diagnostic.severity == Severity::Error
+                && diagnostic.span.offset >= span.offset
+                && diagnostic.span.offset < span.offset + span.length
+        })
+    }
+
+    fn parse_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        self.parse_conditional_expression(ctrl_id)
+    }
+
+    // condition ? expression : expression
+    fn parse_conditional_expression(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_logical_or_expression(ctrl_id)?;
+        let expr_id = self.exprs.peephole(expr_id);
+        if self.current != Some(Token::Question) {
+            return Ok(expr_id);
         }
+        let expr_id = self.check(start, Type::Bool, expr_id);
+        self.consume(); // ?
+        let (then_id, else_id) = self.branch(*ctrl_id, expr_id);
+        let mut then_ctrl_id = then_id;
+        let then_expr_id = self.parse_expression(&mut then_ctrl_id)?;
+        let then_expr_id = self.exprs.peephole(then_expr_id);
+        self.expect(Token::Colon)?;
+        let mut else_ctrl_id = else_id;
+        let else_start = self.span;
+        let else_expr_id = self.parse_conditional_expression(&mut else_ctrl_id)?;
+        let else_expr_id = self.exprs.peephole(else_expr_id);
+        // both sides are brought to a common type
+        let (then_expr_id, else_expr_id) = self.unify(then_expr_id, else_expr_id);
+        let ty = self.exprs.ty(then_expr_id);
+        let else_expr_id = self.check(else_start, ty, else_expr_id);
+        Ok(self.join(
+            ctrl_id,
+            vec![(then_ctrl_id, then_expr_id), (else_ctrl_id, else_expr_id)],
+        ))
     }
 
-    fn parse_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        self.parse_logical_or_expression(ctrl_id)
+    // the value of the rhs of && or ||, if it is a constant that can be used without branching,
+    // which it can't if evaluating it calls a function or changes a variable
+    fn proven(
+        &self,
+        ctrl_id: Id<Ctrl>,
+        branch_id: Id<Ctrl>,
+        rhs_ctrl_id: Id<Ctrl>,
+        rhs_id: Id<Expr>,
+    ) -> Option<bool> {
+        if rhs_ctrl_id != branch_id
+            || self.ctrls.is_dead(branch_id)
+            || self.ctrls.symbols(branch_id) != self.ctrls.symbols(ctrl_id)
+        {
+            return None;
+        }
+        match self.exprs.get(&rhs_id) {
+            Expr::Constant {
+                value: Value::Bool(BoolValue::Constant(rhs)),
+                ..
+            } => Some(*rhs),
+            _ => None,
+        }
     }
 
-    fn parse_logical_or_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_logical_and_expression(ctrl_id);
+    fn parse_logical_or_expression(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_logical_and_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::BarBar) => {
+                    // a || b -> if (a) true else b
+                    let lhs_id = self.check(start, Type::Bool, total_id);
                     self.consume();
-                    let rhs_id = self.parse_logical_and_expression(ctrl_id);
+                    let (then_id, else_id) = self.branch(*ctrl_id, lhs_id);
+                    let mut rhs_ctrl_id = else_id;
+                    let rhs_start = self.span;
+                    let rhs_id = self.parse_logical_and_expression(&mut rhs_ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::LogicalOr,
-                        rhs_id,
-                    })
+                    let rhs_id = self.check(rhs_start, Type::Bool, rhs_id);
+                    // a || false -> a, a || true -> true
+                    if let Some(rhs) = self.proven(*ctrl_id, else_id, rhs_ctrl_id, rhs_id) {
+                        total_id = if rhs { rhs_id } else { lhs_id };
+                        continue;
+                    }
+                    let true_id = self.exprs.alloc(Expr::Constant {
+                        value: Value::Bool(BoolValue::Constant(true)),
+                        ty: Type::Bool,
+                    });
+                    total_id = self.join(ctrl_id, vec![(then_id, true_id), (rhs_ctrl_id, rhs_id)]);
                 }
                 None => {
-                    panic!("logical or expression: unexpected end of source");
+                    return Err(self.error("logical or expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_logical_and_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_or_expression(ctrl_id);
+    fn parse_logical_and_expression(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_or_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::AmpAmp) => {
+                    // a && b -> if (a) b else false
+                    let lhs_id = self.check(start, Type::Bool, total_id);
                     self.consume();
-                    let rhs_id = self.parse_or_expression(ctrl_id);
+                    let (then_id, else_id) = self.branch(*ctrl_id, lhs_id);
+                    let mut rhs_ctrl_id = then_id;
+                    let rhs_start = self.span;
+                    let rhs_id = self.parse_or_expression(&mut rhs_ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::LogicalAnd,
-                        rhs_id,
-                    })
+                    let rhs_id = self.check(rhs_start, Type::Bool, rhs_id);
+                    // a && true -> a, a && false -> false
+                    if let Some(rhs) = self.proven(*ctrl_id, then_id, rhs_ctrl_id, rhs_id) {
+                        total_id = if rhs { lhs_id } else { rhs_id };
+                        continue;
+                    }
+                    let false_id = self.exprs.alloc(Expr::Constant {
+                        value: Value::Bool(BoolValue::Constant(false)),
+                        ty: Type::Bool,
+                    });
+                    total_id = self.join(ctrl_id, vec![(rhs_ctrl_id, rhs_id), (else_id, false_id)]);
                 }
                 None => {
-                    panic!("logical and expression: unexpected end of source");
+                    return Err(self.error("logical and expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_or_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_xor_expression(ctrl_id);
+    fn parse_or_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_xor_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::Bar) => {
                     self.consume();
-                    let rhs_id = self.parse_xor_expression(ctrl_id);
+                    let rhs_id = self.parse_xor_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Or,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Or, rhs_id)
                 }
                 None => {
-                    panic!("binary or expression: unexpected end of source");
+                    return Err(self.error("binary or expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_xor_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_and_expression(ctrl_id);
+    fn parse_xor_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_and_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::Caret) => {
                     self.consume();
-                    let rhs_id = self.parse_and_expression(ctrl_id);
+                    let rhs_id = self.parse_and_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Xor,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Xor, rhs_id)
                 }
                 None => {
-                    panic!("xor expression: unexpected end of source");
+                    return Err(self.error("xor expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_and_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_equality_expression(ctrl_id);
+    fn parse_and_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_equality_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::Amp) => {
                     self.consume();
-                    let rhs_id = self.parse_equality_expression(ctrl_id);
+                    let rhs_id = self.parse_equality_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::And,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::And, rhs_id)
                 }
                 None => {
-                    panic!("binary and expression: unexpected end of source");
+                    return Err(self.error("binary and expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_equality_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_relational_expression(ctrl_id);
+    fn parse_equality_expression(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_relational_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::EqualEqual) => {
                     self.consume();
-                    let rhs_id = self.parse_relational_expression(ctrl_id);
+                    let rhs_id = self.parse_relational_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Equal,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Equal, rhs_id)
                 }
                 Some(Token::ExclEqual) => {
                     self.consume();
-                    let rhs_id = self.parse_relational_expression(ctrl_id);
+                    let rhs_id = self.parse_relational_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::NotEqual,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::NotEqual, rhs_id)
                 }
                 None => {
-                    panic!("equality expression: unexpected end of source");
+                    return Err(self.error("equality expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_relational_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_shift_expression(ctrl_id);
+    fn parse_relational_expression(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_shift_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::Less) => {
                     self.consume();
-                    let rhs_id = self.parse_shift_expression(ctrl_id);
+                    let rhs_id = self.parse_shift_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::LessThan,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::LessThan, rhs_id)
                 }
                 Some(Token::Greater) => {
                     self.consume();
-                    let rhs_id = self.parse_shift_expression(ctrl_id);
+                    let rhs_id = self.parse_shift_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::GreaterThan,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::GreaterThan, rhs_id)
                 }
                 Some(Token::LessEqual) => {
                     self.consume();
-                    let rhs_id = self.parse_shift_expression(ctrl_id);
+                    let rhs_id = self.parse_shift_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::LessThanOrEqual,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::LessThanOrEqual, rhs_id)
                 }
                 Some(Token::GreaterEqual) => {
                     self.consume();
-                    let rhs_id = self.parse_shift_expression(ctrl_id);
+                    let rhs_id = self.parse_shift_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::GreaterThanOrEqual,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::GreaterThanOrEqual, rhs_id)
                 }
                 None => {
-                    panic!("relational expression: unexpected end of source");
+                    return Err(self.error("relational expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_shift_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_additive_expression(ctrl_id);
+    fn parse_shift_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_additive_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::LessLess) => {
                     self.consume();
-                    let rhs_id = self.parse_additive_expression(ctrl_id);
+                    let rhs_id = self.parse_additive_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::ShiftLeft,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::ShiftLeft, rhs_id)
                 }
                 Some(Token::GreaterGreater) => {
                     self.consume();
-                    let rhs_id = self.parse_additive_expression(ctrl_id);
+                    let rhs_id = self.parse_additive_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::ShiftRight,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::ShiftRight, rhs_id)
                 }
                 None => {
-                    panic!("shift expression: unexpected end of source");
+                    return Err(self.error("shift expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_additive_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_multiplicative_expression(ctrl_id);
+    fn parse_additive_expression(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_multiplicative_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::Plus) => {
                     self.consume();
-                    let rhs_id = self.parse_multiplicative_expression(ctrl_id);
+                    let rhs_id = self.parse_multiplicative_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Add,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Add, rhs_id)
                 }
                 Some(Token::Minus) => {
                     self.consume();
-                    let rhs_id = self.parse_multiplicative_expression(ctrl_id);
+                    let rhs_id = self.parse_multiplicative_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Subtract,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Subtract, rhs_id)
                 }
                 None => {
-                    panic!("additive expression: unexpected end of source");
+                    return Err(self.error("additive expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_multiplicative_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
-        let expr_id = self.parse_unary_expression(ctrl_id);
+    fn parse_multiplicative_expression(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let expr_id = self.parse_cast_expression(ctrl_id)?;
         let mut total_id = self.exprs.peephole(expr_id);
         loop {
             match self.current {
                 Some(Token::Star) => {
                     self.consume();
-                    let rhs_id = self.parse_unary_expression(ctrl_id);
+                    let rhs_id = self.parse_cast_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Multiply,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Multiply, rhs_id)
                 }
                 Some(Token::Slash) => {
                     self.consume();
-                    let rhs_id = self.parse_unary_expression(ctrl_id);
+                    let rhs_id = self.parse_cast_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Divide,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Divide, rhs_id)
                 }
                 Some(Token::Percent) => {
                     self.consume();
-                    let rhs_id = self.parse_unary_expression(ctrl_id);
+                    let rhs_id = self.parse_cast_expression(ctrl_id)?;
                     let rhs_id = self.exprs.peephole(rhs_id);
-                    total_id = self.exprs.alloc(Expr::Binary {
-                        lhs_id: total_id,
-                        op: BinaryOp::Modulo,
-                        rhs_id,
-                    })
+                    total_id = self.binary(start, total_id, BinaryOp::Modulo, rhs_id)
                 }
                 None => {
-                    panic!("multiplicative expression: unexpected end of source");
+                    return Err(self.error("multiplicative expression: unexpected end of source"));
                 }
                 _ => break,
             }
         }
-        total_id
+        Ok(total_id)
     }
 
-    fn parse_unary_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
+    // expression as type
+    fn parse_cast_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
+        let mut expr_id = self.parse_unary_expression(ctrl_id)?;
+        while let Some(Token::As) = self.current {
+            self.consume();
+            let expr = self.exprs.peephole(expr_id);
+            let ty = self.parse_type()?;
+            let expr_ty = self.exprs.ty(expr);
+            expr_id = match (expr_ty, ty) {
+                (Type::Int(_) | Type::Float(_), Type::Int(_) | Type::Float(_))
+                | (Type::Bool, Type::Int(_))
+                | (Type::Int(_), Type::Bool) => self.convert(expr, ty),
+                _ if expr_ty == ty => expr,
+                _ => {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("cannot cast `{}` as `{}`", expr_ty, ty),
+                        self.span_from(start),
+                    ));
+                    self.exprs.alloc(Expr::Constant {
+                        value: ty.any(),
+                        ty,
+                    })
+                }
+            };
+        }
+        Ok(expr_id)
+    }
+
+    fn parse_unary_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
+        let start = self.span;
         match self.current {
             Some(Token::Minus) => {
                 self.consume();
-                let expr_id = self.parse_unary_expression(ctrl_id);
+                // -9223372036854775808 is only representable as a whole
+                if let Some(Token::Integer(value)) = self.current {
+                    if value == i64::MIN.unsigned_abs() {
+                        self.consume();
+                        return Ok(self.exprs.alloc(Expr::Constant {
+                            value: Value::Int(IntValue::Constant(i64::MIN)),
+                            ty: Type::Int(IntType::I64),
+                        }));
+                    }
+                }
+                let expr_id = self.parse_unary_expression(ctrl_id)?;
                 let expr_id = self.exprs.peephole(expr_id);
-                self.exprs.alloc(Expr::Unary {
-                    op: UnaryOp::Negate,
-                    expr_id,
-                })
+                Ok(self.unary(start, UnaryOp::Negate, expr_id))
             }
             Some(Token::Excl) => {
                 self.consume();
-                let expr_id = self.parse_unary_expression(ctrl_id);
+                let expr_id = self.parse_unary_expression(ctrl_id)?;
                 let expr_id = self.exprs.peephole(expr_id);
-                self.exprs.alloc(Expr::Unary {
-                    op: UnaryOp::Not,
-                    expr_id,
-                })
+                Ok(self.unary(start, UnaryOp::Not, expr_id))
+            }
+            Some(Token::Tilde) => {
+                self.consume();
+                let expr_id = self.parse_unary_expression(ctrl_id)?;
+                let expr_id = self.exprs.peephole(expr_id);
+                Ok(self.unary(start, UnaryOp::Complement, expr_id))
+            }
+            Some(Token::PlusPlus | Token::MinusMinus) => {
+                let op = if let Some(Token::PlusPlus) = self.current {
+                    BinaryOp::Add
+                } else {
+                    BinaryOp::Subtract
+                };
+                self.consume(); // ++ or --
+                let name = if let Some(Token::Identifier(name)) = &self.current {
+                    name.clone()
+                } else {
+                    return Err(self.error("prefix increment: identifier expected"));
+                };
+                if self.ctrls.symbols(*ctrl_id).get(&name).is_none() {
+                    return Err(self.error(format!("undefined identifier `{}`", name)));
+                }
+                // ++x evaluates to the new value
+                self.consume(); // identifier
+                let (_, new_id) = self.increment(*ctrl_id, start, &name, op);
+                Ok(new_id)
             }
             _ => self.parse_primary_expression(ctrl_id),
         }
     }
 
-    fn parse_primary_expression(&mut self, ctrl_id: Id<Ctrl>) -> Id<Expr> {
+    fn parse_primary_expression(&mut self, ctrl_id: &mut Id<Ctrl>) -> Result<Id<Expr>, Diagnostic> {
         if let Some(Token::OpenParen) = self.current {
             self.consume();
-            let expr_id = self.parse_expression(ctrl_id);
+            let expr_id = self.parse_expression(ctrl_id)?;
             let expr_id = self.exprs.peephole(expr_id);
             if let Some(Token::CloseParen) = self.current {
                 self.consume();
-                expr_id
+                Ok(expr_id)
             } else {
-                panic!("primary expression: `)` expected");
+                Err(self.error("primary expression: `)` expected"))
             }
         } else {
             match &self.current {
                 Some(Token::Integer(value)) => {
-                    let value = *value;
+                    // literals that don't fit in i64 are u64
+                    let ty = if *value > i64::MAX as u64 {
+                        IntType::U64
+                    } else {
+                        IntType::I64
+                    };
+                    let value = *value as i64;
                     self.consume();
-                    self.exprs.alloc(Expr::Constant {
+                    Ok(self.exprs.alloc(Expr::Constant {
                         value: Value::Int(IntValue::Constant(value)),
-                    })
+                        ty: Type::Int(ty),
+                    }))
+                }
+                Some(Token::Float(value)) => {
+                    let value = *value;
+                    self.consume();
+                    Ok(self.exprs.alloc(Expr::Constant {
+                        value: Value::Float(FloatValue::Constant(value)),
+                        ty: Type::Float(FloatType::F64),
+                    }))
+                }
+                Some(Token::True) => {
+                    self.consume();
+                    Ok(self.exprs.alloc(Expr::Constant {
+                        value: Value::Bool(BoolValue::Constant(true)),
+                        ty: Type::Bool,
+                    }))
+                }
+                Some(Token::False) => {
+                    self.consume();
+                    Ok(self.exprs.alloc(Expr::Constant {
+                        value: Value::Bool(BoolValue::Constant(false)),
+                        ty: Type::Bool,
+                    }))
                 }
                 Some(Token::Identifier(name)) => {
                     let name = name.clone();
+                    let span = self.span;
                     self.consume();
-                    if let Some(expr_id) = self.ctrls.symbols(ctrl_id).get(&name) {
-                        expr_id
+                    if let Some(Token::OpenParen) = self.current {
+                        return self.parse_call(ctrl_id, span, name);
+                    }
+                    if let Some(expr_id) = self.lookup(*ctrl_id, &name) {
+                        if let Some(Token::PlusPlus | Token::MinusMinus) = self.current {
+                            let op = if let Some(Token::PlusPlus) = self.current {
+                                BinaryOp::Add
+                            } else {
+                                BinaryOp::Subtract
+                            };
+                            // x++ evaluates to the old value
+                            self.consume(); // ++ or --
+                            let (old_id, _) = self.increment(*ctrl_id, span, &name, op);
+                            Ok(old_id)
+                        } else {
+                            Ok(expr_id)
+                        }
                     } else {
-                        panic!("primary expression: undefined identifier `{}`", name);
+                        Err(Diagnostic::error(
+                            format!("undefined identifier `{}`", name),
+                            span,
+                        ))
                     }
                 }
                 Some(token) => {
-                    panic!("primary expression: unexpected `{}`", token);
-                }
-                None => {
-                    panic!("primary expression: unexpected end of source");
+                    Err(self.error(format!("primary expression: unexpected `{}`", token)))
                 }
+                None => Err(self.error("primary expression: unexpected end of source")),
             }
         }
     }
+
+    // name(argument, ...), the call continues control after the arguments are evaluated
+    fn parse_call(
+        &mut self,
+        ctrl_id: &mut Id<Ctrl>,
+        start: Span,
+        name: String,
+    ) -> Result<Id<Expr>, Diagnostic> {
+        let (param_tys, ty) = match self.signatures.get(&name) {
+            Some(signature) => signature.clone(),
+            None => {
+                return Err(Diagnostic::error(
+                    format!("undefined function `{}`", name),
+                    start,
+                ))
+            }
+        };
+        self.consume(); // (
+        let mut arg_ids = Vec::new();
+        while self.current != Some(Token::CloseParen) {
+            if !arg_ids.is_empty() {
+                self.expect(Token::Comma)?;
+            }
+            let arg_start = self.span;
+            let arg_id = self.parse_expression(ctrl_id)?;
+            let arg_id = self.exprs.peephole(arg_id);
+            let arg_id = match param_tys.get(arg_ids.len()) {
+                Some(param_ty) => self.check(arg_start, *param_ty, arg_id),
+                None => arg_id,
+            };
+            arg_ids.push(arg_id);
+        }
+        self.consume(); // )
+        if arg_ids.len() != param_tys.len() {
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "function `{}` takes {} argument{} but {} {} supplied",
+                    name,
+                    param_tys.len(),
+                    if param_tys.len() == 1 { "" } else { "s" },
+                    arg_ids.len(),
+                    if arg_ids.len() == 1 { "was" } else { "were" },
+                ),
+                self.span_from(start),
+            ));
+        }
+        let call_id = self.ctrls.alloc(Ctrl::Call {
+            ctrl_id: *ctrl_id,
+            name,
+            start_id: None,
+            arg_ids,
+        });
+        self.call_ids.push(call_id);
+        let symbols = self.ctrls.symbols(*ctrl_id).clone();
+        *ctrl_id = self.ctrls.alloc(Ctrl::CallEnd {
+            ctrl_id: call_id,
+            symbols,
+        });
+        // the result is projected out of the end of the call
+        Ok(self.exprs.alloc(Expr::Proj {
+            ctrl_id: *ctrl_id,
+            index: 0,
+            ty,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn messages(source: &str, severity: Severity) -> Vec<String> {
+        let mut parser = Parser::new(source);
+        let _ = parser.parse_program();
+        parser
+            .diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        messages(source, Severity::Error)
+    }
+
+    // the parser, and the control and the value of each return in main
+    fn parse_returns(source: &str) -> (Parser<'_>, Vec<(Id<Ctrl>, Id<Expr>)>) {
+        let mut parser = Parser::new(source);
+        let functions = parser.parse_program().unwrap();
+        let main = functions
+            .iter()
+            .find(|function| function.name == "main")
+            .unwrap();
+        let Ctrl::Stop { ctrl_ids } = parser.ctrls.get(&main.stop_id) else {
+            panic!("functions end in Stop");
+        };
+        let returns = ctrl_ids
+            .iter()
+            .map(|ctrl_id| match parser.ctrls.get(ctrl_id) {
+                Ctrl::Return { ctrl_id, expr_id } => (*ctrl_id, *expr_id),
+                ctrl => panic!("{:?} in Stop", ctrl),
+            })
+            .collect();
+        (parser, returns)
+    }
+
+    // the value of each return in main
+    fn returns(source: &str) -> Vec<Value> {
+        let (parser, returns) = parse_returns(source);
+        returns
+            .iter()
+            .map(|(_, expr_id)| parser.exprs.compute(*expr_id))
+            .collect()
+    }
+
+    fn int(value: i64) -> Value {
+        Value::Int(IntValue::Constant(value))
+    }
+
+    fn bool(value: bool) -> Value {
+        Value::Bool(BoolValue::Constant(value))
+    }
+
+    #[test]
+    fn errors_in_separate_statements_are_all_reported() {
+        assert_eq!(
+            errors("fn main() -> int { int a = 1 +; int b = ); return 0; }"),
+            vec![
+                "primary expression: unexpected `;`",
+                "primary expression: unexpected `)`",
+            ]
+        );
+    }
+
+    #[test]
+    fn recovery_skips_nested_blocks() {
+        let source = "
+            fn main(int a) -> int {
+                while (a < ) { if (a > 1) { a = 1; } a = 2; }
+                if (a + ) { a = 1; } else { a = 2; }
+                return a;
+            }
+            fn other() -> int { return 1; }";
+        assert_eq!(
+            errors(source),
+            vec![
+                "primary expression: unexpected `)`",
+                "primary expression: unexpected `)`",
+            ]
+        );
+    }
+
+    #[test]
+    fn recovery_continues_with_the_next_function() {
+        let source = "fn main() -> int { return 1 } fn other() -> int { return true; }";
+        assert_eq!(
+            errors(source),
+            vec![
+                "expected `;`, got `}`",
+                "mismatched types: expected `i64`, found `bool`",
+            ]
+        );
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let source = "fn main() -> int {
+            int x = 5; x += 3; x -= 1; x *= 4; x /= 2; x %= 10; x <<= 3; x >>= 1;
+            x |= 1; x &= 7; x ^= 2; return x; }";
+        assert_eq!(returns(source), vec![int(3)]);
+    }
+
+    #[test]
+    fn compound_assignment_reads_the_lhs_first() {
+        assert_eq!(
+            returns("fn main() -> int { int x = 1; x += x++; return x; }"),
+            vec![int(2)]
+        );
+    }
+
+    #[test]
+    fn compound_assignment_is_type_checked() {
+        assert_eq!(
+            errors("fn main() -> int { bool b = true; b += 1; return 0; }"),
+            vec!["binary operator `+` cannot be applied to `bool` and `i64`"]
+        );
+    }
+
+    #[test]
+    fn prefix_and_postfix_increment() {
+        let source =
+            "fn main() -> int { int x = 5; int a = x++; int b = ++x; int c = x--; int d = --x;
+            return a * 1000 + b * 100 + c * 10 + d; }";
+        assert_eq!(returns(source), vec![int(5000 + 700 + 70 + 5)]);
+        assert_eq!(
+            returns("fn main() -> int { int x = 1; x++; ++x; x--; return x; }"),
+            vec![int(2)]
+        );
+    }
+
+    #[test]
+    fn increment_needs_a_variable() {
+        assert_eq!(
+            errors("fn main() -> int { return ++1; }"),
+            vec!["prefix increment: identifier expected"]
+        );
+        assert_eq!(
+            errors("fn main() -> int { y++; return 0; }"),
+            vec!["undefined identifier `y`"]
+        );
+    }
+
+    #[test]
+    fn boolean_literals_and_operators() {
+        assert_eq!(
+            returns("fn main() -> bool { bool a = true; bool b = a && !a; return b; }"),
+            vec![bool(false)]
+        );
+        assert_eq!(
+            returns("fn main(bool a) -> bool { return (a & false) | (a ^ false) | !a; }"),
+            vec![Value::Bool(BoolValue::Any)]
+        );
+        assert_eq!(
+            returns("fn main() -> bool { return 1 < 2 == true; }"),
+            vec![bool(true)]
+        );
+        assert_eq!(
+            returns("fn main(bool a) -> bool { return a != a; }"),
+            vec![bool(false)]
+        );
+    }
+
+    #[test]
+    fn ill_typed_operators_are_reported_once() {
+        assert_eq!(
+            errors("fn main(int a) -> int { return a + true; }"),
+            vec!["binary operator `+` cannot be applied to `i64` and `bool`"]
+        );
+        assert_eq!(
+            errors("fn main(int a) -> int { int b = (a < true) * 2; return b; }"),
+            vec!["binary operator `<` cannot be applied to `i64` and `bool`"]
+        );
+        assert_eq!(
+            errors("fn main(bool b) -> bool { return (b + 1) && b; }"),
+            vec!["binary operator `+` cannot be applied to `bool` and `i64`"]
+        );
+    }
+
+    #[test]
+    fn if_else_merges_variables() {
+        let source = "fn main(int a) -> int { int x = 0; if (a > 0) x = 1; else x = 2; return x; }";
+        assert_eq!(returns(source), vec![Value::Int(IntValue::range(1, 2))]);
+        let source = "fn main(int a) -> int { int x = 3; if (a > 0) { x = 4; } return x; }";
+        assert_eq!(returns(source), vec![Value::Int(IntValue::range(3, 4))]);
+        let source =
+            "fn main(int a) -> int { int x = 3; if (a > 0) { x = 4; } else { x = 4; } return x; }";
+        assert_eq!(returns(source), vec![int(4)]);
+    }
+
+    #[test]
+    fn if_with_a_constant_condition_takes_one_branch() {
+        let source = "fn main() -> int { int x = 0; if (true) x = 1; else x = 2; return x; }";
+        assert_eq!(returns(source), vec![int(1)]);
+        let source = "fn main() -> int { int x = 0; if (1 > 2) { x = 1; } return x; }";
+        assert_eq!(returns(source), vec![int(0)]);
+    }
+
+    #[test]
+    fn if_branches_have_their_own_scope() {
+        assert_eq!(
+            errors("fn main(int a) -> int { if (a > 0) { int y = 1; } return y; }"),
+            vec!["undefined identifier `y`"]
+        );
+    }
+
+    #[test]
+    fn if_condition_is_bool() {
+        assert_eq!(
+            errors("fn main(int a) -> int { if (a) { a = 1; } return a; }"),
+            vec!["mismatched types: expected `bool`, found `i64`"]
+        );
+    }
+
+    #[test]
+    fn while_loop_variables() {
+        // changed in the loop
+        let source = "fn main(int n) -> int { int x = 0; while (x < n) x = x + 1; return x; }";
+        assert_eq!(returns(source), vec![Value::Int(IntType::I64.any())]);
+        // unchanged, or changed to the value it already had
+        let source = "fn main(int n) -> int { int x = 7; int y = 5;
+            while (n > 0) { n = n - 1; y = 5; } return x + y; }";
+        assert_eq!(returns(source), vec![int(12)]);
+        // the body never runs
+        let source = "fn main() -> int { int x = 1; while (false) { x = 2; } return x; }";
+        assert_eq!(returns(source), vec![int(1)]);
+    }
+
+    #[test]
+    fn while_loops_nest() {
+        let source = "fn main(int n) -> int { int s = 0; int i = 0;
+            while (i < n) { int j = 0; while (j < i) { s = s + 1; j = j + 1; } i = i + 1; }
+            return s; }";
+        assert_eq!(returns(source), vec![Value::Int(IntType::I64.any())]);
+    }
+
+    #[test]
+    fn while_errors() {
+        assert_eq!(
+            errors("fn main(int a) -> int { while (a) { a = a - 1; } return a; }"),
+            vec!["mismatched types: expected `bool`, found `i64`"]
+        );
+        assert_eq!(
+            errors("fn main(int a) -> int { while (a > 0) { int b = a; a = a - 1; } return b; }"),
+            vec!["undefined identifier `b`"]
+        );
+    }
+
+    fn warnings(source: &str) -> Vec<String> {
+        messages(source, Severity::Warning)
+    }
+
+    #[test]
+    fn break_leaves_the_loop() {
+        let source = "fn main() -> int { int x = 0; while (true) { x = 1; break; } return x; }";
+        assert_eq!(returns(source), vec![int(1)]);
+        let source = "fn main(int n) -> int { int x = 0;
+            'outer: while (true) { while (n > 0) { x = 2; break 'outer; } x = 2; break; }
+            return x; }";
+        assert_eq!(returns(source), vec![int(2)]);
+    }
+
+    #[test]
+    fn continue_goes_back_to_the_top() {
+        let source = "fn main(int n) -> int { int x = 4;
+            while (n > 0) { n = n - 1; if (n > 3) { x = 4; continue; } x = 4; }
+            return x; }";
+        assert_eq!(returns(source), vec![int(4)]);
+        let source = "fn main(int n) -> int { int x = 4;
+            while (n > 0) { n = n - 1; if (n > 3) { x = 5; continue; } }
+            return x; }";
+        assert_eq!(returns(source), vec![Value::Int(IntType::I64.any())]);
+    }
+
+    #[test]
+    fn break_and_continue_errors() {
+        assert_eq!(
+            errors("fn main() -> int { break; return 0; }"),
+            vec!["`break` outside of a loop"]
+        );
+        assert_eq!(
+            errors("fn main() -> int { continue; return 0; }"),
+            vec!["`continue` outside of a loop"]
+        );
+        assert_eq!(
+            errors("fn main() -> int { 'a: while (true) { break 'b; } return 0; }"),
+            vec!["undeclared label `'b`"]
+        );
+        assert_eq!(
+            warnings("fn main() -> int { while (true) { break; int x = 1; } return 0; }"),
+            vec!["unreachable code after `break`"]
+        );
+    }
+
+    #[test]
+    fn for_loops() {
+        let source = "fn main(int n) -> int { int s = 0;
+            for (int i = 0; i < n; i++) { s += i; } return s; }";
+        assert_eq!(returns(source), vec![Value::Int(IntType::I64.any())]);
+        let source = "fn main(int n) -> int { int x = 3;
+            for (; n > 0; n = n - 1) { x = 3; } for (;;) { break; } return x; }";
+        assert_eq!(returns(source), vec![int(3)]);
+        // continue still runs the step
+        let source =
+            "fn main() -> int { int i = 0; for (i = 0; false; i++) { continue; } return i; }";
+        assert_eq!(returns(source), vec![int(0)]);
+    }
+
+    #[test]
+    fn do_while_runs_the_body_first() {
+        let source = "fn main() -> int { int x = 0; do { x = x + 1; } while (false); return x; }";
+        assert_eq!(returns(source), vec![int(1)]);
+        let source = "fn main(int n) -> int { int x = 0; do { x = 2; n = n - 1; } while (n > 0);
+            return x; }";
+        assert_eq!(returns(source), vec![int(2)]);
+    }
+
+    #[test]
+    fn for_variable_is_scoped_to_the_loop() {
+        assert_eq!(
+            errors("fn main(int a) -> int { for (int i = 0; i < a; i++) {} return i; }"),
+            vec!["undefined identifier `i`"]
+        );
+        // also when the header doesn't parse
+        assert_eq!(
+            errors("fn main(int a) -> int { for (int i = 0; i < ; i++) {} return i; }"),
+            vec![
+                "primary expression: unexpected `;`",
+                "undefined identifier `i`",
+            ]
+        );
+    }
+
+    #[test]
+    fn for_step_ends_at_the_parenthesis() {
+        assert_eq!(
+            errors("fn main(int a) -> int { for (int i = 0; i < a; i++ x) { a++; } return a; }"),
+            vec!["expected `)`, got `x`"]
+        );
+    }
+
+    #[test]
+    fn for_and_do_while_errors() {
+        assert_eq!(
+            errors("fn main() -> int { for (1; true;) {} return 0; }"),
+            vec!["for statement: declaration or assignment expected"]
+        );
+        assert_eq!(
+            errors("fn main(int a) -> int { do { a = 1; } while (a); return a; }"),
+            vec!["mismatched types: expected `bool`, found `i64`"]
+        );
+    }
+
+    #[test]
+    fn for_header_errors_are_reported_once() {
+        // the header is scanned ahead of parsing, which must not lose or repeat errors
+        let count = |source, message| {
+            errors(source)
+                .iter()
+                .filter(|error| *error == message)
+                .count()
+        };
+        let source = "fn main() -> int { for (int i = 0; i < 3; i += 1 /* x) {} return 0; }";
+        assert_eq!(count(source, "unterminated block comment"), 1);
+        let source = "fn main() -> int { for (int i = 0; i < 3; i += @) {} return 0; }";
+        assert_eq!(count(source, "unexpected character `@`"), 1);
+    }
+
+    #[test]
+    fn calls() {
+        let source = "fn main(int n) -> int { return twice(n) + twice(1); }
+            fn twice(int a) -> int { return a * 2; }";
+        assert!(errors(source).is_empty());
+        assert_eq!(returns(source), vec![Value::Int(IntType::I64.any())]);
+    }
+
+    #[test]
+    fn call_errors() {
+        let source = "fn f(int a, bool b) -> int { return a; }
+            fn main() -> int { return f(1) + f(1, 2) + g(); }";
+        assert_eq!(
+            errors(source),
+            vec![
+                "function `f` takes 2 arguments but 1 was supplied",
+                "mismatched types: expected `bool`, found `i64`",
+                "undefined function `g`",
+            ]
+        );
+        assert_eq!(
+            errors("fn f() -> int { return 1; } fn f() -> int { return 2; }"),
+            vec!["function `f` is defined more than once"]
+        );
+        assert_eq!(
+            errors("fn f() -> bool { return true; } fn main() -> int { return f(); }"),
+            vec!["mismatched types: expected `i64`, found `bool`"]
+        );
+    }
+
+    #[test]
+    fn call_statements() {
+        let source = "fn f(int n) -> int { return n; } fn main(int a) -> int { f(a); return a; }";
+        let (parser, returned) = parse_returns(source);
+        assert!(parser.diagnostics().is_empty());
+        assert!(matches!(
+            parser.ctrls.get(&returned[0].0),
+            Ctrl::CallEnd { .. }
+        ));
+        assert_eq!(
+            errors("fn main() -> int { g(1); return 0; }"),
+            vec!["undefined function `g`"]
+        );
+    }
+
+    #[test]
+    fn every_return_ends_up_in_stop() {
+        let source =
+            "fn main(int a) -> int { if (a > 0) { return 1; } else if (a < 0) { return 2; }
+            return 3; }";
+        assert_eq!(returns(source), vec![int(1), int(2), int(3)]);
+        // a return on a branch that is never taken is dropped
+        let source = "fn main() -> int { if (false) { return 1; } return 2; }";
+        assert_eq!(returns(source), vec![int(2)]);
+    }
+
+    #[test]
+    fn missing_returns() {
+        assert_eq!(
+            errors("fn main(int a) -> int { a = 1; }"),
+            vec!["function `main`: return statement expected"]
+        );
+        assert_eq!(
+            errors("fn main(int a) -> int { if (a > 0) { return 1; } }"),
+            vec!["function `main`: missing return on some path"]
+        );
+        assert!(errors("fn main() -> int { while (true) { return 1; } }").is_empty());
+    }
+
+    #[test]
+    fn code_after_return_is_unreachable() {
+        assert_eq!(
+            warnings("fn main() -> int { return 1; int a = 2; }"),
+            vec!["unreachable code after `return`"]
+        );
+        assert!(
+            warnings("fn main(int a) -> int { if (a > 0) { return 1; } return 2; }").is_empty()
+        );
+    }
+
+    #[test]
+    fn logical_rhs_is_evaluated_on_its_branch() {
+        let f = "fn f(int n) -> bool { return n > 1; }";
+        let source = format!("{f} fn main(int a) -> bool {{ return a > 0 && f(a); }}");
+        let parser = parse_returns(&source).0;
+        let Ctrl::Call { ctrl_id, .. } = parser.ctrls.get(&parser.call_ids[0]) else {
+            panic!("calls are Call nodes");
+        };
+        assert!(matches!(parser.ctrls.get(ctrl_id), Ctrl::Then { .. }));
+        let source = format!("{f} fn main(int a) -> bool {{ return a > 0 || f(a); }}");
+        let parser = parse_returns(&source).0;
+        let Ctrl::Call { ctrl_id, .. } = parser.ctrls.get(&parser.call_ids[0]) else {
+            panic!("calls are Call nodes");
+        };
+        assert!(matches!(parser.ctrls.get(ctrl_id), Ctrl::Else { .. }));
+    }
+
+    #[test]
+    fn logical_result_is_a_phi_of_the_lhs_constant_and_the_rhs() {
+        for (op, short) in [("&&", false), ("||", true)] {
+            let source = format!("fn main(int a, int b) -> bool {{ return a > 0 {op} b > 0; }}");
+            let (parser, returned) = parse_returns(&source);
+            let (ctrl_id, expr_id) = returned[0];
+            let Expr::Phi { ctrl, expr_ids, .. } = parser.exprs.get(&expr_id) else {
+                panic!("{op} is not a Phi");
+            };
+            assert_eq!(*ctrl, ctrl_id);
+            assert!(matches!(parser.ctrls.get(ctrl), Ctrl::Merge { .. }));
+            let (rhs_id, lhs_id) = if short {
+                (expr_ids[1], expr_ids[0])
+            } else {
+                (expr_ids[0], expr_ids[1])
+            };
+            assert_eq!(parser.exprs.compute(lhs_id), bool(short));
+            assert!(matches!(
+                parser.exprs.get(&rhs_id),
+                Expr::Binary {
+                    op: BinaryOp::GreaterThan,
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn logical_with_a_constant_lhs_takes_one_branch() {
+        let f = "fn f(int n) -> bool { return n > 1; }";
+        for (expression, value) in [("false && f(a)", false), ("true || f(a)", true)] {
+            let source = format!("{f} fn main(int a) -> bool {{ return {expression}; }}");
+            let (parser, returned) = parse_returns(&source);
+            assert_eq!(parser.exprs.compute(returned[0].1), bool(value));
+            assert!(!matches!(
+                parser.ctrls.get(&returned[0].0),
+                Ctrl::Merge { .. }
+            ));
+            let Ctrl::Call { ctrl_id, .. } = parser.ctrls.get(&parser.call_ids[0]) else {
+                panic!("calls are Call nodes");
+            };
+            assert!(parser.ctrls.is_dead(*ctrl_id));
+        }
+        assert_eq!(
+            returns("fn main(int a) -> bool { return true && a > 0; }"),
+            returns("fn main(int a) -> bool { return a > 0; }")
+        );
+    }
+
+    #[test]
+    fn logical_with_a_constant_rhs_is_simplified() {
+        for expression in ["a > 1 && true", "a > 1 || false"] {
+            let source = format!("fn main(int a) -> bool {{ return {expression}; }}");
+            let (parser, returned) = parse_returns(&source);
+            assert!(matches!(
+                parser.exprs.get(&returned[0].1),
+                Expr::Binary {
+                    op: BinaryOp::GreaterThan,
+                    ..
+                }
+            ));
+            assert!(matches!(
+                parser.ctrls.get(&returned[0].0),
+                Ctrl::Start { .. }
+            ));
+        }
+        assert_eq!(
+            returns("fn main(int a) -> bool { return a > 1 && false; }"),
+            vec![bool(false)]
+        );
+        assert_eq!(
+            returns("fn main(int a) -> bool { return a > 1 || true; }"),
+            vec![bool(true)]
+        );
+        // changing a variable on the rhs needs the branch
+        let (parser, returned) =
+            parse_returns("fn main(int a) -> bool { return a > 1 && (a++ > 0 || true); }");
+        assert!(matches!(parser.exprs.get(&returned[0].1), Expr::Phi { .. }));
+    }
+
+    #[test]
+    fn conditional_expression() {
+        let source = "fn main(bool c, int a) -> int { return c ? a : 2; }";
+        let (parser, returned) = parse_returns(source);
+        let (ctrl_id, expr_id) = returned[0];
+        let Expr::Phi { ctrl, expr_ids, .. } = parser.exprs.get(&expr_id) else {
+            panic!("?: is not a Phi");
+        };
+        assert_eq!(*ctrl, ctrl_id);
+        assert!(matches!(parser.ctrls.get(ctrl), Ctrl::Merge { .. }));
+        assert!(matches!(parser.exprs.get(&expr_ids[0]), Expr::Proj { .. }));
+        assert_eq!(parser.exprs.compute(expr_ids[1]), int(2));
+    }
+
+    #[test]
+    fn conditional_with_a_constant_condition_takes_one_arm() {
+        let source = "fn main(int a) -> int { return true ? a : 2; }";
+        let (parser, returned) = parse_returns(source);
+        assert!(matches!(
+            parser.exprs.get(&returned[0].1),
+            Expr::Proj { .. }
+        ));
+        assert!(!matches!(
+            parser.ctrls.get(&returned[0].0),
+            Ctrl::Merge { .. }
+        ));
+        assert_eq!(
+            returns("fn main() -> int { return 1 > 2 ? 1 : 2; }"),
+            vec![int(2)]
+        );
+    }
+
+    #[test]
+    fn conditional_is_right_associative() {
+        // read as (false ? 1 : true) ? 2 : 3 the arms wouldn't match
+        assert_eq!(
+            returns("fn main() -> int { return false ? 1 : true ? 2 : 3; }"),
+            vec![int(2)]
+        );
+        let source = "fn main(bool c, bool d) -> int { return c ? 1 : d ? 2 : 3; }";
+        let (parser, returned) = parse_returns(source);
+        let Expr::Phi { expr_ids, .. } = parser.exprs.get(&returned[0].1) else {
+            panic!("?: is not a Phi");
+        };
+        assert_eq!(parser.exprs.compute(expr_ids[0]), int(1));
+        assert!(matches!(parser.exprs.get(&expr_ids[1]), Expr::Phi { .. }));
+    }
+
+    #[test]
+    fn conditional_precedence() {
+        // the condition takes in all of ||, and an assignment takes in all of ?:
+        assert_eq!(
+            returns("fn main() -> int { return false || true ? 1 : 2; }"),
+            vec![int(1)]
+        );
+        assert_eq!(
+            returns("fn main() -> int { int x = 0; x = false ? 3 : 4; return x; }"),
+            vec![int(4)]
+        );
+    }
+
+    #[test]
+    fn conditional_type_errors() {
+        assert_eq!(
+            errors("fn main(bool c) -> int { return c ? 1 : true; }"),
+            vec!["mismatched types: expected `i64`, found `bool`"]
+        );
+        assert_eq!(
+            errors("fn main(bool c) -> int { return c ? true : 1; }"),
+            vec!["mismatched types: expected `bool`, found `i64`"]
+        );
+        assert_eq!(
+            errors("fn main(int a) -> int { return a ? 1 : 2; }"),
+            vec!["mismatched types: expected `bool`, found `i64`"]
+        );
+    }
+
+    #[test]
+    fn conditional_arms_have_a_common_type() {
+        for arms in ["s : b", "b : s"] {
+            let source = format!("fn main(bool c, i8 s, i64 b) -> i64 {{ return c ? {arms}; }}");
+            let (parser, returned) = parse_returns(&source);
+            assert!(parser.diagnostics().is_empty());
+            assert_eq!(parser.exprs.ty(returned[0].1), Type::Int(IntType::I64));
+        }
+    }
+
+    #[test]
+    fn ill_typed_unary_operators_are_reported_once() {
+        assert_eq!(
+            errors("fn main(int a) -> int { return !a; }"),
+            vec!["unary operator `!` cannot be applied to `i64`"]
+        );
+        assert_eq!(
+            errors("fn main(bool b) -> bool { return ~b; }"),
+            vec!["unary operator `~` cannot be applied to `bool`"]
+        );
+        assert_eq!(
+            errors("fn main(int a) -> int { return !a + 1; }"),
+            vec!["unary operator `!` cannot be applied to `i64`"]
+        );
+    }
+
+    #[test]
+    fn float_operands_are_type_checked() {
+        assert_eq!(
+            errors("fn main(int a) -> int { return a + 1.5; }"),
+            vec!["binary operator `+` cannot be applied to `i64` and `f64`"]
+        );
+        assert_eq!(
+            errors("fn main(f64 x) -> f64 { return ~x; }"),
+            vec!["unary operator `~` cannot be applied to `f64`"]
+        );
+        assert_eq!(
+            returns("fn main() -> f64 { f64 x = 1.5; return x * 2.0; }"),
+            vec![Value::Float(FloatValue::Constant(3.0))]
+        );
+    }
 }
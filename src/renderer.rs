@@ -0,0 +1,258 @@
+use crate::*;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+
+// columns a tab is expanded to, in the printed line and in the underline alike
+const TAB_WIDTH: usize = 4;
+
+pub struct Renderer<'a> {
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    // plain output, suitable for tests
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            color: false,
+        }
+    }
+
+    // output with ANSI colors, suitable for terminals
+    pub fn colored(source: &'a str) -> Self {
+        Self {
+            source,
+            color: true,
+        }
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn severity_style(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => CYAN,
+        }
+    }
+
+    // the text of line `line` (1-based), without line terminator
+    fn line(&self, line: usize) -> &'a str {
+        self.source
+            .split('\n')
+            .nth(line - 1)
+            .unwrap_or("")
+            .trim_end_matches('\r')
+    }
+
+    // number of columns to underline for span, clipped to the end of its line
+    fn width(&self, span: &Span) -> usize {
+        let start = span.offset.min(self.source.len());
+        let end = (span.offset + span.length).min(self.source.len());
+        let width = columns(self.source[start..end].chars().take_while(|c| *c != '\n'));
+        width.max(1)
+    }
+
+    // number of columns before span on its line
+    fn indent(&self, span: &Span) -> usize {
+        columns(self.line(span.line).chars().take(span.column - 1))
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let style = Self::severity_style(diagnostic.severity);
+
+        // primary span first, then the secondary labels, ordered by position
+        let mut marks: Vec<(&Span, Option<&str>, bool)> = vec![(&diagnostic.span, None, true)];
+        for label in diagnostic.labels.iter() {
+            marks.push((&label.span, Some(&label.message), false));
+        }
+        marks.sort_by_key(|(span, _, _)| (span.line, span.column));
+
+        let gutter = marks
+            .iter()
+            .map(|(span, _, _)| span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let empty = format!("{} |", " ".repeat(gutter));
+
+        let mut result = format!(
+            "{}{}\n",
+            self.paint(style, &diagnostic.severity.to_string()),
+            self.paint(BOLD, &format!(": {}", diagnostic.message)),
+        );
+        result.push_str(&format!(
+            "{}{} {}:{}\n",
+            " ".repeat(gutter),
+            self.paint(BLUE, "-->"),
+            diagnostic.span.line,
+            diagnostic.span.column
+        ));
+        result.push_str(&format!("{}\n", self.paint(BLUE, &empty)));
+
+        let mut previous: Option<usize> = None;
+        for (span, message, primary) in marks.iter() {
+            if previous != Some(span.line) {
+                if let Some(previous) = previous {
+                    if span.line > previous + 1 {
+                        result.push_str(&format!("{}\n", self.paint(BLUE, "...")));
+                    }
+                }
+                result.push_str(&format!(
+                    "{} {}\n",
+                    self.paint(BLUE, &format!("{:>gutter$} |", span.line)),
+                    self.line(span.line).replace('\t', &" ".repeat(TAB_WIDTH))
+                ));
+                previous = Some(span.line);
+            }
+            let (marker, marker_style) = if *primary { ("^", style) } else { ("-", BLUE) };
+            let mut underline = marker.repeat(self.width(span));
+            if let Some(message) = message {
+                underline.push(' ');
+                underline.push_str(message);
+            }
+            result.push_str(&format!(
+                "{} {}{}\n",
+                self.paint(BLUE, &empty),
+                " ".repeat(self.indent(span)),
+                self.paint(marker_style, &underline)
+            ));
+        }
+
+        for note in diagnostic.notes.iter() {
+            result.push_str(&format!(
+                "{} {}: {}\n",
+                self.paint(BLUE, &format!("{} =", " ".repeat(gutter))),
+                self.paint(BOLD, "note"),
+                note
+            ));
+        }
+        result
+    }
+}
+
+// printed width of text, with tabs expanded
+fn columns(text: impl Iterator<Item = char>) -> usize {
+    text.map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    // the span of the first occurrence of `text` in `source`
+    fn span(source: &str, text: &str) -> Span {
+        let offset = source.find(text).unwrap();
+        let before = &source[..offset];
+        Span {
+            offset,
+            length: text.len(),
+            line: before.matches('\n').count() + 1,
+            column: before.chars().rev().take_while(|c| *c != '\n').count() + 1,
+        }
+    }
+
+    #[test]
+    fn primary_span() {
+        let source = "int a = true;";
+        let diagnostic = Diagnostic::error(
+            "mismatched types: expected `i64`, found `bool`",
+            span(source, "true"),
+        );
+        assert_eq!(
+            Renderer::new(source).render(&diagnostic),
+            "\
+error: mismatched types: expected `i64`, found `bool`
+ --> 1:9
+  |
+1 | int a = true;
+  |         ^^^^
+"
+        );
+    }
+
+    #[test]
+    fn tabs_are_expanded() {
+        let source = "\tint a = true;";
+        let diagnostic = Diagnostic::error("mismatched types", span(source, "true"));
+        assert_eq!(
+            Renderer::new(source).render(&diagnostic),
+            "\
+error: mismatched types
+ --> 1:10
+  |
+1 |     int a = true;
+  |             ^^^^
+"
+        );
+    }
+
+    #[test]
+    fn label_on_another_line() {
+        let source = "int a = 1;\nint b = 2;\n\nint a = 3;";
+        let diagnostic = Diagnostic::error("`a` is declared twice", span(source, "a = 3"))
+            .with_label(span(source, "a"), "first declared here");
+        assert_eq!(
+            Renderer::new(source).render(&diagnostic),
+            "\
+error: `a` is declared twice
+ --> 4:5
+  |
+1 | int a = 1;
+  |     - first declared here
+...
+4 | int a = 3;
+  |     ^^^^^
+"
+        );
+    }
+
+    #[test]
+    fn gutter_fits_the_widest_line_number() {
+        let source = format!("{}return x;", "\n".repeat(11));
+        let diagnostic = Diagnostic::error("undefined identifier `x`", span(&source, "x"))
+            .with_label(span(&source, "return"), "in this return");
+        assert_eq!(
+            Renderer::new(&source).render(&diagnostic),
+            "\
+error: undefined identifier `x`
+  --> 12:8
+   |
+12 | return x;
+   | ------ in this return
+   |        ^
+"
+        );
+    }
+
+    #[test]
+    fn notes() {
+        let source = "x = 1;";
+        let diagnostic = Diagnostic::error("undefined identifier `x`", span(source, "x"))
+            .with_note("variables are declared with a type")
+            .with_note("like `int x = 1;`");
+        assert_eq!(
+            Renderer::new(source).render(&diagnostic),
+            "\
+error: undefined identifier `x`
+ --> 1:1
+  |
+1 | x = 1;
+  | ^
+  = note: variables are declared with a type
+  = note: like `int x = 1;`
+"
+        );
+    }
+}
@@ -1,8 +1,15 @@
 use {crate::*, std::collections::HashMap};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Expr(Id<Expr>),
+    // unchanged since entering the loop, the phi is created on first use
+    Lazy(Id<Ctrl>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Symbols {
-    symbolses: Vec<HashMap<String, Id<Expr>>>,
+    symbolses: Vec<HashMap<String, Binding>>,
 }
 
 impl Symbols {
@@ -24,13 +31,14 @@ impl Symbols {
         self.symbolses
             .last_mut()
             .unwrap()
-            .insert(name.to_string(), expr_id);
+            .insert(name.to_string(), Binding::Expr(expr_id));
     }
 
-    pub fn get(&self, name: &str) -> Option<Id<Expr>> {
-        for symbols in self.symbolses.iter().rev() {
-            if let Some(expr_id) = symbols.get(name) {
-                return Some(*expr_id);
+    // the binding of a name, with the depth of the scope it lives in
+    pub fn get(&self, name: &str) -> Option<(usize, Binding)> {
+        for (depth, symbols) in self.symbolses.iter().enumerate().rev() {
+            if let Some(binding) = symbols.get(name) {
+                return Some((depth, *binding));
             }
         }
         None
@@ -39,9 +47,55 @@ impl Symbols {
     pub fn set(&mut self, name: &str, expr_id: Id<Expr>) {
         for symbols in self.symbolses.iter_mut().rev() {
             if let Some(symbol) = symbols.get_mut(name) {
-                *symbol = expr_id;
+                *symbol = Binding::Expr(expr_id);
                 return;
             }
         }
     }
+
+    pub fn depth(&self) -> usize {
+        self.symbolses.len()
+    }
+
+    pub fn truncate(&mut self, depth: usize) {
+        self.symbolses.truncate(depth);
+    }
+
+    // all visible and shadowed names, with the depth of the scope they live in
+    pub fn names(&self) -> Vec<(usize, String)> {
+        let mut names = Vec::new();
+        for (depth, symbols) in self.symbolses.iter().enumerate() {
+            for name in symbols.keys() {
+                names.push((depth, name.clone()));
+            }
+        }
+        names
+    }
+
+    pub fn get_at(&self, depth: usize, name: &str) -> Option<Binding> {
+        self.symbolses[depth].get(name).copied()
+    }
+
+    pub fn set_at(&mut self, depth: usize, name: &str, binding: Binding) {
+        self.symbolses[depth].insert(name.to_string(), binding);
+    }
+
+    // mark every name as unchanged since entering loop `loop_id`
+    pub fn make_lazy(&mut self, loop_id: Id<Ctrl>) {
+        for symbols in self.symbolses.iter_mut() {
+            for binding in symbols.values_mut() {
+                *binding = Binding::Lazy(loop_id);
+            }
+        }
+    }
+
+    pub fn replace(&mut self, old_id: Id<Expr>, new_id: Id<Expr>) {
+        for symbols in self.symbolses.iter_mut() {
+            for binding in symbols.values_mut() {
+                if *binding == Binding::Expr(old_id) {
+                    *binding = Binding::Expr(new_id);
+                }
+            }
+        }
+    }
 }
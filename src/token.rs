@@ -1,11 +1,13 @@
 use std::fmt::Display;
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 
 pub enum Token {
     Eof,
-    Integer(i64),
+    Integer(u64),
+    Float(f64),
     Identifier(String),
+    Label(String),
     Plus,
     Minus,
     Star,
@@ -20,11 +22,24 @@ pub enum Token {
     PlusPlus,
     MinusMinus,
     MinusGreater,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    AmpEqual,
+    BarEqual,
+    CaretEqual,
+    LessLessEqual,
+    GreaterGreaterEqual,
     OpenParen,
     CloseParen,
     OpenBrace,
     CloseBrace,
     Semicolon,
+    Colon,
+    Comma,
+    Question,
     Equal,
     EqualEqual,
     Excl,
@@ -38,8 +53,27 @@ pub enum Token {
     Fn,
     Return,
     Int,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Bool,
+    True,
+    False,
     If,
     Else,
+    While,
+    For,
+    Do,
+    Break,
+    Continue,
+    As,
 }
 
 impl Display for Token {
@@ -47,7 +81,9 @@ impl Display for Token {
         match self {
             Token::Eof => write!(f, "EOF"),
             Token::Integer(value) => write!(f, "{}", value),
+            Token::Float(value) => write!(f, "{:?}", value),
             Token::Identifier(value) => write!(f, "{}", value),
+            Token::Label(value) => write!(f, "'{}", value),
             Token::Plus => write!(f, "+"),
             Token::Minus => write!(f, "-"),
             Token::Star => write!(f, "*"),
@@ -62,11 +98,24 @@ impl Display for Token {
             Token::PlusPlus => write!(f, "++"),
             Token::MinusMinus => write!(f, "--"),
             Token::MinusGreater => write!(f, "->"),
+            Token::PlusEqual => write!(f, "+="),
+            Token::MinusEqual => write!(f, "-="),
+            Token::StarEqual => write!(f, "*="),
+            Token::SlashEqual => write!(f, "/="),
+            Token::PercentEqual => write!(f, "%="),
+            Token::AmpEqual => write!(f, "&="),
+            Token::BarEqual => write!(f, "|="),
+            Token::CaretEqual => write!(f, "^="),
+            Token::LessLessEqual => write!(f, "<<="),
+            Token::GreaterGreaterEqual => write!(f, ">>="),
             Token::OpenParen => write!(f, "("),
             Token::CloseParen => write!(f, ")"),
             Token::OpenBrace => write!(f, "{{"),
             Token::CloseBrace => write!(f, "}}"),
             Token::Semicolon => write!(f, ";"),
+            Token::Colon => write!(f, ":"),
+            Token::Comma => write!(f, ","),
+            Token::Question => write!(f, "?"),
             Token::Equal => write!(f, "="),
             Token::Excl => write!(f, "!"),
             Token::EqualEqual => write!(f, "=="),
@@ -80,8 +129,27 @@ impl Display for Token {
             Token::Fn => write!(f, "fn"),
             Token::Return => write!(f, "return"),
             Token::Int => write!(f, "int"),
+            Token::I8 => write!(f, "i8"),
+            Token::I16 => write!(f, "i16"),
+            Token::I32 => write!(f, "i32"),
+            Token::I64 => write!(f, "i64"),
+            Token::U8 => write!(f, "u8"),
+            Token::U16 => write!(f, "u16"),
+            Token::U32 => write!(f, "u32"),
+            Token::U64 => write!(f, "u64"),
+            Token::F32 => write!(f, "f32"),
+            Token::F64 => write!(f, "f64"),
+            Token::Bool => write!(f, "bool"),
+            Token::True => write!(f, "true"),
+            Token::False => write!(f, "false"),
             Token::If => write!(f, "if"),
             Token::Else => write!(f, "else"),
+            Token::While => write!(f, "while"),
+            Token::For => write!(f, "for"),
+            Token::Do => write!(f, "do"),
+            Token::Break => write!(f, "break"),
+            Token::Continue => write!(f, "continue"),
+            Token::As => write!(f, "as"),
         }
     }
 }
@@ -3,11 +3,14 @@ use {
     std::{iter::Iterator, str::Chars},
 };
 
+#[derive(Clone)]
 pub struct Tokenizer<'a> {
     source: Chars<'a>,
     peeked: Option<char>,
+    offset: usize,
     line: usize,
     column: usize,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -17,181 +20,641 @@ impl<'a> Tokenizer<'a> {
         Self {
             source,
             peeked,
+            offset: 0,
             line: 1,
             column: 1,
+            diagnostics: Vec::new(),
         }
     }
 
     fn consume(&mut self) {
+        if let Some(c) = self.peeked {
+            self.offset += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
         self.peeked = self.source.next();
     }
+
+    // the fraction and exponent of a float literal, after its integer digits in `text`
+    fn float(
+        &mut self,
+        mut text: String,
+        offset: usize,
+        line: usize,
+        column: usize,
+    ) -> (Token, Span) {
+        if let Some('.') = self.peeked {
+            self.consume();
+            text.push('.');
+            if !self.digits(&mut text) {
+                text.push('0');
+            }
+        }
+        let mut exponent = true;
+        if let Some('e' | 'E') = self.peeked {
+            self.consume();
+            text.push('e');
+            if let Some(c @ ('+' | '-')) = self.peeked {
+                self.consume();
+                text.push(c);
+            }
+            exponent = self.digits(&mut text);
+        }
+        let mut invalid: Option<char> = None;
+        while let Some(c) = self.peeked {
+            if c.is_alphanumeric() {
+                self.consume();
+                invalid.get_or_insert(c);
+            } else {
+                break;
+            }
+        }
+        let span = self.span(offset, line, column);
+        let value = text.parse::<f64>().unwrap_or(0.0);
+        if let Some(c) = invalid {
+            self.diagnostics.push(Diagnostic::error(
+                format!("invalid digit `{}` in float literal", c),
+                span,
+            ));
+        } else if !exponent {
+            self.diagnostics
+                .push(Diagnostic::error("missing digits in float exponent", span));
+        } else if value.is_infinite() {
+            self.diagnostics
+                .push(Diagnostic::error("float literal is too large", span));
+        } else {
+            return (Token::Float(value), span);
+        }
+        (Token::Float(0.0), span)
+    }
+
+    // decimal digits and underscores, returns whether there were any digits
+    fn digits(&mut self, text: &mut String) -> bool {
+        let mut digits = false;
+        while let Some(c) = self.peeked {
+            if c.is_ascii_digit() {
+                self.consume();
+                text.push(c);
+                digits = true;
+            } else if c == '_' {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        digits
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub fn position(&self) -> Span {
+        self.span(self.offset, self.line, self.column)
+    }
+
+    fn span(&self, offset: usize, line: usize, column: usize) -> Span {
+        Span {
+            offset,
+            length: self.offset - offset,
+            line,
+            column,
+        }
+    }
 }
 
 impl<'a> Iterator for Tokenizer<'a> {
-    type Item = Token;
+    type Item = (Token, Span);
 
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(c) = self.peeked {
+            let (offset, line, column) = (self.offset, self.line, self.column);
             match c {
                 // whitespace
-                ' ' | '\t' | '\n' => {
+                ' ' | '\t' | '\r' | '\n' => {
                     self.consume();
-                    match c {
-                        ' ' => self.column += 1,
-                        '\t' => self.column += 8,
-                        '\n' => {
-                            self.column = 1;
-                            self.line += 1;
-                        }
-                        _ => {}
-                    }
                 }
 
-                // integer
+                // integer or float
                 '0'..='9' => {
                     self.consume();
-                    self.column += 1;
-                    let mut number = c.to_digit(10).unwrap() as i64;
+                    let mut text = c.to_string();
+                    let mut radix = 10;
+                    let mut number = c.to_digit(10).unwrap() as u64;
+                    let mut digits = 1;
+                    if c == '0' {
+                        let prefix = match self.peeked {
+                            Some('x') => Some(16),
+                            Some('b') => Some(2),
+                            Some('o') => Some(8),
+                            _ => None,
+                        };
+                        if let Some(prefix) = prefix {
+                            self.consume();
+                            radix = prefix;
+                            digits = 0;
+                        }
+                    }
+                    let mut overflow = false;
+                    let mut invalid: Option<char> = None;
                     while let Some(c) = self.peeked {
-                        if c.is_digit(10) {
+                        if radix == 10 && invalid.is_none() && matches!(c, '.' | 'e' | 'E') {
+                            return Some(self.float(text, offset, line, column));
+                        } else if c == '_' {
+                            self.consume();
+                        } else if c.is_alphanumeric() {
                             self.consume();
-                            self.column += 1;
-                            number = number * 10 + c.to_digit(10).unwrap() as i64;
+                            if let Some(digit) = c.to_digit(radix) {
+                                text.push(c);
+                                digits += 1;
+                                match number
+                                    .checked_mul(radix as u64)
+                                    .and_then(|number| number.checked_add(digit as u64))
+                                {
+                                    Some(result) => number = result,
+                                    None => overflow = true,
+                                }
+                            } else if invalid.is_none() {
+                                invalid = Some(c);
+                            }
                         } else {
                             break;
                         }
                     }
-                    return Some(Token::Integer(number));
+                    let span = self.span(offset, line, column);
+                    if let Some(c) = invalid {
+                        let kind = match radix {
+                            16 => "hexadecimal",
+                            8 => "octal",
+                            2 => "binary",
+                            _ => "decimal",
+                        };
+                        self.diagnostics.push(Diagnostic::error(
+                            format!("invalid digit `{}` in {} literal", c, kind),
+                            span,
+                        ));
+                        number = 0;
+                    } else if digits == 0 {
+                        self.diagnostics.push(Diagnostic::error(
+                            "missing digits after integer prefix",
+                            span,
+                        ));
+                    } else if overflow {
+                        self.diagnostics
+                            .push(Diagnostic::error("integer literal is too large", span));
+                        number = 0;
+                    }
+                    return Some((Token::Integer(number), span));
                 }
 
                 // identifier or keyword
                 'A'..='Z' | 'a'..='z' => {
                     self.consume();
-                    self.column += 1;
                     let mut identifier = c.to_string();
                     while let Some(c) = self.peeked {
                         if c.is_alphanumeric() {
                             self.consume();
-                            self.column += 1;
                             identifier.push(c);
                         } else {
                             break;
                         }
                     }
                     match identifier.as_str() {
-                        "fn" => return Some(Token::Fn),
-                        "return" => return Some(Token::Return),
-                        "int" => return Some(Token::Int),
-                        "if" => return Some(Token::If),
-                        "else" => return Some(Token::Else),
-                        _ => return Some(Token::Identifier(identifier)),
+                        "fn" => return Some((Token::Fn, self.span(offset, line, column))),
+                        "return" => return Some((Token::Return, self.span(offset, line, column))),
+                        "int" => return Some((Token::Int, self.span(offset, line, column))),
+                        "i8" => return Some((Token::I8, self.span(offset, line, column))),
+                        "i16" => return Some((Token::I16, self.span(offset, line, column))),
+                        "i32" => return Some((Token::I32, self.span(offset, line, column))),
+                        "i64" => return Some((Token::I64, self.span(offset, line, column))),
+                        "u8" => return Some((Token::U8, self.span(offset, line, column))),
+                        "u16" => return Some((Token::U16, self.span(offset, line, column))),
+                        "u32" => return Some((Token::U32, self.span(offset, line, column))),
+                        "u64" => return Some((Token::U64, self.span(offset, line, column))),
+                        "f32" => return Some((Token::F32, self.span(offset, line, column))),
+                        "f64" => return Some((Token::F64, self.span(offset, line, column))),
+                        "bool" => return Some((Token::Bool, self.span(offset, line, column))),
+                        "true" => return Some((Token::True, self.span(offset, line, column))),
+                        "false" => return Some((Token::False, self.span(offset, line, column))),
+                        "if" => return Some((Token::If, self.span(offset, line, column))),
+                        "else" => return Some((Token::Else, self.span(offset, line, column))),
+                        "while" => return Some((Token::While, self.span(offset, line, column))),
+                        "for" => return Some((Token::For, self.span(offset, line, column))),
+                        "do" => return Some((Token::Do, self.span(offset, line, column))),
+                        "break" => return Some((Token::Break, self.span(offset, line, column))),
+                        "as" => return Some((Token::As, self.span(offset, line, column))),
+                        "continue" => {
+                            return Some((Token::Continue, self.span(offset, line, column)))
+                        }
+                        _ => {
+                            return Some((
+                                Token::Identifier(identifier),
+                                self.span(offset, line, column),
+                            ))
+                        }
+                    }
+                }
+
+                // label
+                '\'' => {
+                    self.consume();
+                    let mut label = String::new();
+                    while let Some(c) = self.peeked {
+                        if c.is_alphanumeric() {
+                            self.consume();
+                            label.push(c);
+                        } else {
+                            break;
+                        }
+                    }
+                    let span = self.span(offset, line, column);
+                    if label.is_empty() {
+                        self.diagnostics
+                            .push(Diagnostic::error("expected label name after `'`", span));
+                        continue;
+                    }
+                    return Some((Token::Label(label), span));
+                }
+
+                // comment or slash
+                '/' => {
+                    self.consume();
+                    match self.peeked {
+                        Some('/') => {
+                            // line comment
+                            while let Some(c) = self.peeked {
+                                if c == '\n' {
+                                    break;
+                                }
+                                self.consume();
+                            }
+                        }
+                        Some('*') => {
+                            // block comment, possibly nested
+                            self.consume();
+                            let opening = self.span(offset, line, column);
+                            let mut depth = 1;
+                            while depth > 0 {
+                                match self.peeked {
+                                    Some('*') => {
+                                        self.consume();
+                                        if let Some('/') = self.peeked {
+                                            self.consume();
+                                            depth -= 1;
+                                        }
+                                    }
+                                    Some('/') => {
+                                        self.consume();
+                                        if let Some('*') = self.peeked {
+                                            self.consume();
+                                            depth += 1;
+                                        }
+                                    }
+                                    Some(_) => self.consume(),
+                                    None => {
+                                        self.diagnostics.push(Diagnostic::error(
+                                            "unterminated block comment",
+                                            opening,
+                                        ));
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                        Some('=') => {
+                            self.consume();
+                            return Some((Token::SlashEqual, self.span(offset, line, column)));
+                        }
+                        _ => return Some((Token::Slash, self.span(offset, line, column))),
                     }
                 }
 
                 // punctuation
-                '(' | ')' | '{' | '}' | ';' | '+' | '-' | '*' | '/' | '=' | '!' | '<' | '>' => {
+                '(' | ')' | '{' | '}' | ';' | ':' | ',' | '?' | '+' | '-' | '*' | '%' | '|'
+                | '&' | '^' | '~' | '=' | '!' | '<' | '>' => {
                     self.consume();
-                    self.column += 1;
-                    match c {
-                        '(' => return Some(Token::OpenParen),
-                        ')' => return Some(Token::CloseParen),
-                        '{' => return Some(Token::OpenBrace),
-                        '}' => return Some(Token::CloseBrace),
-                        ';' => return Some(Token::Semicolon),
-                        '*' => return Some(Token::Star),
-                        '/' => return Some(Token::Slash),
+                    let token = match c {
+                        '(' => Token::OpenParen,
+                        ')' => Token::CloseParen,
+                        '{' => Token::OpenBrace,
+                        '}' => Token::CloseBrace,
+                        ';' => Token::Semicolon,
+                        ':' => Token::Colon,
+                        ',' => Token::Comma,
+                        '?' => Token::Question,
+                        '~' => Token::Tilde,
+                        '*' => match self.peeked {
+                            Some('=') => {
+                                self.consume();
+                                Token::StarEqual
+                            }
+                            _ => Token::Star,
+                        },
+                        '%' => match self.peeked {
+                            Some('=') => {
+                                self.consume();
+                                Token::PercentEqual
+                            }
+                            _ => Token::Percent,
+                        },
+                        '^' => match self.peeked {
+                            Some('=') => {
+                                self.consume();
+                                Token::CaretEqual
+                            }
+                            _ => Token::Caret,
+                        },
                         '|' => match self.peeked {
                             Some('|') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::BarBar);
+                                Token::BarBar
+                            }
+                            Some('=') => {
+                                self.consume();
+                                Token::BarEqual
                             }
-                            _ => return Some(Token::Bar),
+                            _ => Token::Bar,
                         },
                         '&' => match self.peeked {
                             Some('&') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::AmpAmp);
+                                Token::AmpAmp
                             }
-                            _ => return Some(Token::Amp),
+                            Some('=') => {
+                                self.consume();
+                                Token::AmpEqual
+                            }
+                            _ => Token::Amp,
                         },
-                        '^' => return Some(Token::Caret),
-                        '~' => return Some(Token::Tilde),
-                        '%' => return Some(Token::Percent),
                         '+' => match self.peeked {
                             Some('+') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::PlusPlus);
+                                Token::PlusPlus
+                            }
+                            Some('=') => {
+                                self.consume();
+                                Token::PlusEqual
                             }
-                            _ => return Some(Token::Plus),
+                            _ => Token::Plus,
                         },
                         '-' => match self.peeked {
                             Some('>') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::MinusGreater);
+                                Token::MinusGreater
                             }
                             Some('-') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::MinusMinus);
+                                Token::MinusMinus
+                            }
+                            Some('=') => {
+                                self.consume();
+                                Token::MinusEqual
                             }
-                            _ => return Some(Token::Minus),
+                            _ => Token::Minus,
                         },
                         '=' => match self.peeked {
                             Some('=') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::EqualEqual);
+                                Token::EqualEqual
                             }
-                            _ => return Some(Token::Equal),
+                            _ => Token::Equal,
                         },
                         '!' => match self.peeked {
                             Some('=') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::ExclEqual);
+                                Token::ExclEqual
                             }
-                            _ => return Some(Token::Excl),
+                            _ => Token::Excl,
                         },
                         '<' => match self.peeked {
                             Some('=') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::LessEqual);
+                                Token::LessEqual
                             }
                             Some('<') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::LessLess);
+                                match self.peeked {
+                                    Some('=') => {
+                                        self.consume();
+                                        Token::LessLessEqual
+                                    }
+                                    _ => Token::LessLess,
+                                }
                             }
-                            _ => return Some(Token::Less),
+                            _ => Token::Less,
                         },
-                        '>' => match self.peeked {
+                        _ => match self.peeked {
                             Some('=') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::GreaterEqual);
+                                Token::GreaterEqual
                             }
                             Some('>') => {
                                 self.consume();
-                                self.column += 1;
-                                return Some(Token::GreaterGreater);
+                                match self.peeked {
+                                    Some('=') => {
+                                        self.consume();
+                                        Token::GreaterGreaterEqual
+                                    }
+                                    _ => Token::GreaterGreater,
+                                }
                             }
-                            _ => return Some(Token::Greater),
+                            _ => Token::Greater,
                         },
-                        _ => {}
-                    }
+                    };
+                    return Some((token, self.span(offset, line, column)));
                 }
                 _ => {
                     self.consume();
-                    self.column += 1;
-                    println!("{}:{}: unexpected character: {}", self.line, self.column, c);
+                    let span = self.span(offset, line, column);
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("unexpected character `{}`", c),
+                        span,
+                    ));
                 }
             }
         }
         return None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        Tokenizer::new(source).map(|(token, _)| token).collect()
+    }
+
+    fn spans(source: &str) -> Vec<(usize, usize, usize, usize)> {
+        Tokenizer::new(source)
+            .map(|(_, span)| (span.offset, span.length, span.line, span.column))
+            .collect()
+    }
+
+    #[test]
+    fn spans_cover_each_token() {
+        assert_eq!(
+            tokens("x = 12;"),
+            vec![
+                Token::Identifier("x".to_string()),
+                Token::Equal,
+                Token::Integer(12),
+                Token::Semicolon,
+            ]
+        );
+        assert_eq!(
+            spans("x = 12;\n  return x"),
+            vec![
+                (0, 1, 1, 1),
+                (2, 1, 1, 3),
+                (4, 2, 1, 5),
+                (6, 1, 1, 7),
+                (10, 6, 2, 3),
+                (17, 1, 2, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_count_bytes_but_columns_count_characters() {
+        let mut tokenizer = Tokenizer::new("é x");
+        let spans: Vec<Span> = tokenizer.by_ref().map(|(_, span)| span).collect();
+        assert_eq!((spans[0].offset, spans[0].column), (3, 3));
+        let diagnostics = tokenizer.take_diagnostics();
+        assert_eq!(diagnostics[0].message, "unexpected character `é`");
+        assert_eq!(
+            (diagnostics[0].span.offset, diagnostics[0].span.length),
+            (0, 2)
+        );
+    }
+
+    #[test]
+    fn position_is_the_end_of_the_source() {
+        let mut tokenizer = Tokenizer::new("a\nbc");
+        while tokenizer.next().is_some() {}
+        assert_eq!(
+            tokenizer.position(),
+            Span {
+                offset: 4,
+                length: 0,
+                line: 2,
+                column: 3,
+            }
+        );
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        let mut tokenizer = Tokenizer::new(source);
+        while tokenizer.next().is_some() {}
+        tokenizer
+            .take_diagnostics()
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn line_comments_run_to_the_end_of_the_line() {
+        assert_eq!(
+            tokens("a // b c\nd"),
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::Identifier("d".to_string()),
+            ]
+        );
+        assert_eq!(tokens("// only a comment"), vec![]);
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        assert_eq!(
+            tokens("a /* b /* c */ d */ e"),
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::Identifier("e".to_string()),
+            ]
+        );
+        assert_eq!(spans("/* é\n */ x"), vec![(10, 1, 2, 5)]);
+        assert!(errors("a /* b /* c */ d */ e").is_empty());
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_reported() {
+        assert_eq!(
+            tokens("a /* b /* c */"),
+            vec![Token::Identifier("a".to_string())]
+        );
+        assert_eq!(errors("a /* b /* c */"), vec!["unterminated block comment"]);
+    }
+
+    #[test]
+    fn integer_literals_in_every_radix() {
+        assert_eq!(
+            tokens("255 0xff 0xFF 0o377 0b1111_1111 1_000"),
+            vec![
+                Token::Integer(255),
+                Token::Integer(255),
+                Token::Integer(255),
+                Token::Integer(255),
+                Token::Integer(255),
+                Token::Integer(1000),
+            ]
+        );
+        assert_eq!(
+            tokens("0xffff_ffff_ffff_ffff"),
+            vec![Token::Integer(u64::MAX)]
+        );
+    }
+
+    #[test]
+    fn bad_integer_literals_are_reported() {
+        assert_eq!(errors("0b102"), vec!["invalid digit `2` in binary literal"]);
+        assert_eq!(errors("0o8"), vec!["invalid digit `8` in octal literal"]);
+        assert_eq!(
+            errors("0xfg"),
+            vec!["invalid digit `g` in hexadecimal literal"]
+        );
+        assert_eq!(errors("12a"), vec!["invalid digit `a` in decimal literal"]);
+        assert_eq!(errors("0x"), vec!["missing digits after integer prefix"]);
+        assert_eq!(
+            errors("18446744073709551616"),
+            vec!["integer literal is too large"]
+        );
+        // one token per bad literal, so parsing can go on
+        assert_eq!(tokens("0b102 x").len(), 2);
+    }
+
+    #[test]
+    fn float_literals() {
+        assert_eq!(
+            tokens("1.5 2. 1e10 1.5e-3 2E+2 1_0.2_5"),
+            vec![
+                Token::Float(1.5),
+                Token::Float(2.0),
+                Token::Float(1e10),
+                Token::Float(1.5e-3),
+                Token::Float(200.0),
+                Token::Float(10.25),
+            ]
+        );
+        assert!(errors("1.5 2. 1e10 1.5e-3").is_empty());
+    }
+
+    #[test]
+    fn bad_float_literals_are_reported() {
+        assert_eq!(errors("1e"), vec!["missing digits in float exponent"]);
+        assert_eq!(errors("1e+ x"), vec!["missing digits in float exponent"]);
+        assert_eq!(errors("1e400"), vec!["float literal is too large"]);
+        assert_eq!(errors("1.5x"), vec!["invalid digit `x` in float literal"]);
+        assert_eq!(errors("2e3_a"), vec!["invalid digit `a` in float literal"]);
+        assert_eq!(
+            tokens("1e x"),
+            vec![Token::Float(0.0), Token::Identifier("x".to_string())]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use {crate::*, std::fmt::Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntType {
+    pub fn bits(&self) -> u32 {
+        match self {
+            IntType::I8 | IntType::U8 => 8,
+            IntType::I16 | IntType::U16 => 16,
+            IntType::I32 | IntType::U32 => 32,
+            IntType::I64 | IntType::U64 => 64,
+        }
+    }
+
+    pub fn signed(&self) -> bool {
+        match self {
+            IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64 => true,
+            IntType::U8 | IntType::U16 | IntType::U32 | IntType::U64 => false,
+        }
+    }
+
+    // smallest and largest value, u64 values above i64::MAX are kept as their i64 bit pattern
+    pub fn bounds(&self) -> (i64, i64) {
+        match self {
+            IntType::I8 => (i8::MIN as i64, i8::MAX as i64),
+            IntType::I16 => (i16::MIN as i64, i16::MAX as i64),
+            IntType::I32 => (i32::MIN as i64, i32::MAX as i64),
+            IntType::U8 => (0, u8::MAX as i64),
+            IntType::U16 => (0, u16::MAX as i64),
+            IntType::U32 => (0, u32::MAX as i64),
+            IntType::I64 | IntType::U64 => (i64::MIN, i64::MAX),
+        }
+    }
+
+    // wrap a value around to this type
+    pub fn wrap(&self, value: i64) -> i64 {
+        match self {
+            IntType::I8 => value as i8 as i64,
+            IntType::I16 => value as i16 as i64,
+            IntType::I32 => value as i32 as i64,
+            IntType::U8 => value as u8 as i64,
+            IntType::U16 => value as u16 as i64,
+            IntType::U32 => value as u32 as i64,
+            IntType::I64 | IntType::U64 => value,
+        }
+    }
+
+    // wrap a lattice value around to this type, keeping what is known about the low bits
+    pub fn wrap_value(&self, value: &IntValue) -> IntValue {
+        if let IntValue::Constant(a) = value {
+            return IntValue::Constant(self.wrap(*a));
+        }
+        let (min, max) = self.bounds();
+        match (value.bounds(), value.known_bits()) {
+            (Some((value_min, value_max)), _) if value_min >= min && value_max <= max => {
+                value.clone()
+            }
+            (_, Some(bits)) => IntValue::new(min, max, self.wrap_bits(bits)),
+            _ => IntValue::All,
+        }
+    }
+
+    fn wrap_bits(&self, bits: KnownBits) -> KnownBits {
+        if self.bits() == 64 {
+            return bits;
+        }
+        let low = (1 << self.bits()) - 1;
+        let sign = 1 << (self.bits() - 1);
+        let mut zeros = bits.zeros & low;
+        let mut ones = bits.ones & low;
+        // the high bits are zero, or copies of the sign bit
+        if !self.signed() || zeros & sign != 0 {
+            zeros |= !low;
+        } else if ones & sign != 0 {
+            ones |= !low;
+        }
+        KnownBits { zeros, ones }
+    }
+
+    // a value of this type that isn't known at compile time
+    pub fn any(&self) -> IntValue {
+        self.wrap_value(&IntValue::Any)
+    }
+
+    // whether constant `value` of type `from` has the same value in this type
+    pub fn holds(&self, from: IntType, value: i64) -> bool {
+        if from == IntType::U64 && value < 0 {
+            *self == IntType::U64
+        } else if *self == IntType::U64 {
+            value >= 0
+        } else {
+            let (min, max) = self.bounds();
+            value >= min && value <= max
+        }
+    }
+
+    // convert a float to this type, saturating at the bounds with NaN going to 0
+    pub fn from_float(&self, value: f64) -> i64 {
+        match self {
+            IntType::I8 => value as i8 as i64,
+            IntType::I16 => value as i16 as i64,
+            IntType::I32 => value as i32 as i64,
+            IntType::I64 => value as i64,
+            IntType::U8 => value as u8 as i64,
+            IntType::U16 => value as u16 as i64,
+            IntType::U32 => value as u32 as i64,
+            IntType::U64 => value as u64 as i64,
+        }
+    }
+
+    // the actual value of `value` of this type
+    pub fn to_i128(&self, value: i64) -> i128 {
+        if *self == IntType::U64 {
+            value as u64 as i128
+        } else {
+            value as i128
+        }
+    }
+
+    // whether every value of this type is also a value of type `to`
+    pub fn widens_to(&self, to: IntType) -> bool {
+        match (self.signed(), to.signed()) {
+            (true, true) | (false, false) => self.bits() <= to.bits(),
+            (false, true) => self.bits() < to.bits(),
+            (true, false) => false,
+        }
+    }
+
+    // the smallest type both types widen to
+    pub fn common(&self, other: IntType) -> Option<IntType> {
+        if self.widens_to(other) {
+            Some(other)
+        } else if other.widens_to(*self) {
+            Some(*self)
+        } else {
+            // a signed and a wider unsigned type
+            [IntType::I16, IntType::I32, IntType::I64]
+                .into_iter()
+                .find(|ty| self.widens_to(*ty) && other.widens_to(*ty))
+        }
+    }
+}
+
+impl Display for IntType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntType::I8 => write!(f, "i8"),
+            IntType::I16 => write!(f, "i16"),
+            IntType::I32 => write!(f, "i32"),
+            IntType::I64 => write!(f, "i64"),
+            IntType::U8 => write!(f, "u8"),
+            IntType::U16 => write!(f, "u16"),
+            IntType::U32 => write!(f, "u32"),
+            IntType::U64 => write!(f, "u64"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatType {
+    F32,
+    F64,
+}
+
+impl FloatType {
+    // round a value to this type
+    pub fn round(&self, value: f64) -> f64 {
+        match self {
+            FloatType::F32 => value as f32 as f64,
+            FloatType::F64 => value,
+        }
+    }
+
+    // whether constant `value` can be rounded to this type without overflowing
+    pub fn holds(&self, value: f64) -> bool {
+        !value.is_finite() || self.round(value).is_finite()
+    }
+
+    // whether constant `value` of type `from` is exactly representable in this type
+    pub fn holds_int(&self, from: IntType, value: i64) -> bool {
+        let value = from.to_i128(value);
+        let rounded = match self {
+            FloatType::F32 => value as f32 as f64,
+            FloatType::F64 => value as f64,
+        };
+        rounded as i128 == value
+    }
+
+    // convert integer `value` of type `from` to this type
+    pub fn from_int(&self, from: IntType, value: i64) -> f64 {
+        match self {
+            FloatType::F32 => from.to_i128(value) as f32 as f64,
+            FloatType::F64 => from.to_i128(value) as f64,
+        }
+    }
+}
+
+impl Display for FloatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FloatType::F32 => write!(f, "f32"),
+            FloatType::F64 => write!(f, "f64"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Int(IntType),
+    Float(FloatType),
+}
+
+impl Type {
+    // the value of an expression of this type that isn't known at compile time
+    pub fn any(&self) -> Value {
+        match self {
+            Type::Bool => Value::Bool(BoolValue::Any),
+            Type::Int(ty) => Value::Int(ty.any()),
+            Type::Float(_) => Value::Float(FloatValue::Any),
+        }
+    }
+
+    // whether every value of this type is also a value of type `to`
+    pub fn widens_to(&self, to: Type) -> bool {
+        match (self, to) {
+            (Type::Int(from), Type::Int(to)) => from.widens_to(to),
+            (Type::Float(FloatType::F32), Type::Float(_)) => true,
+            _ => *self == to,
+        }
+    }
+
+    // the smallest type both types widen to
+    pub fn common(&self, other: Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Int(a), Type::Int(b)) => a.common(b).map(Type::Int),
+            (Type::Float(FloatType::F32), Type::Float(FloatType::F32)) => {
+                Some(Type::Float(FloatType::F32))
+            }
+            (Type::Float(_), Type::Float(_)) => Some(Type::Float(FloatType::F64)),
+            _ if *self == other => Some(other),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::Int(ty) => write!(f, "{}", ty),
+            Type::Float(ty) => write!(f, "{}", ty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(IntType::I8.wrap(200), -56);
+        assert_eq!(IntType::U8.wrap(-1), 255);
+        assert_eq!(IntType::U32.wrap(1 << 32), 0);
+        assert_eq!(
+            IntType::I8.wrap_value(&IntValue::Constant(200)),
+            IntValue::Constant(-56)
+        );
+        assert_eq!(
+            IntType::I8.wrap_value(&IntValue::range(0, 100)),
+            IntValue::range(0, 100)
+        );
+        assert_eq!(
+            IntType::U8.wrap_value(&IntValue::range(250, 260)),
+            IntType::U8.any()
+        );
+        // the low bits survive wrapping
+        let shifted = IntValue::Any.shl(&IntValue::Constant(8));
+        assert_eq!(IntType::I8.wrap_value(&shifted), IntValue::Constant(0));
+    }
+
+    #[test]
+    fn holds() {
+        assert!(IntType::I8.holds(IntType::I64, 127));
+        assert!(!IntType::I8.holds(IntType::I64, 128));
+        assert!(!IntType::U64.holds(IntType::I64, -1));
+        assert!(!IntType::I64.holds(IntType::U64, -1));
+        assert!(IntType::U64.holds(IntType::U64, -1));
+        assert_eq!(IntType::U64.to_i128(-1), u64::MAX as i128);
+    }
+
+    #[test]
+    fn widening() {
+        assert!(IntType::U8.widens_to(IntType::I16));
+        assert!(!IntType::U8.widens_to(IntType::I8));
+        assert!(!IntType::I8.widens_to(IntType::U64));
+        assert!(IntType::U32.widens_to(IntType::U64));
+        assert_eq!(IntType::I8.common(IntType::U8), Some(IntType::I16));
+        assert_eq!(IntType::U16.common(IntType::I64), Some(IntType::I64));
+        assert_eq!(IntType::I32.common(IntType::U64), None);
+    }
+
+    #[test]
+    fn from_float() {
+        assert_eq!(IntType::U8.from_float(300.0), 255);
+        assert_eq!(IntType::I32.from_float(f64::NAN), 0);
+        assert_eq!(IntType::U64.from_float(-1.0), 0);
+        assert_eq!(IntType::I8.from_float(-2.7), -2);
+    }
+
+    #[test]
+    fn float_types() {
+        assert!(!FloatType::F32.holds(1e39));
+        assert!(FloatType::F32.holds(f64::INFINITY));
+        assert!(FloatType::F64.holds(1e39));
+        assert!(!FloatType::F32.holds_int(IntType::I32, 16_777_217));
+        assert!(FloatType::F64.holds_int(IntType::I32, 16_777_217));
+        assert!(!FloatType::F64.holds_int(IntType::U64, -1));
+        assert_eq!(
+            FloatType::F32.from_int(IntType::U64, -1),
+            18446744073709551616.0
+        );
+        let (f32, f64) = (Type::Float(FloatType::F32), Type::Float(FloatType::F64));
+        assert!(f32.widens_to(f64));
+        assert!(!f64.widens_to(f32));
+        assert_eq!(f32.common(f64), Some(f64));
+        assert_eq!(f32.common(Type::Int(IntType::I32)), None);
+    }
+}
@@ -1,9 +1,42 @@
-use std::fmt::Display;
+use {crate::*, std::fmt::Display};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
     Negate,
     Not,
+    Complement,
+}
+
+impl UnaryOp {
+    // result type of the operator, or None if it can't be applied to this operand type
+    pub fn ty(&self, expr: Type) -> Option<Type> {
+        match self {
+            UnaryOp::Negate => match expr {
+                Type::Int(ty) if ty.signed() => Some(expr),
+                Type::Float(_) => Some(expr),
+                _ => None,
+            },
+            UnaryOp::Not => {
+                if expr == Type::Bool {
+                    Some(Type::Bool)
+                } else {
+                    None
+                }
+            }
+            UnaryOp::Complement => match expr {
+                Type::Int(_) => Some(expr),
+                _ => None,
+            },
+        }
+    }
+
+    // result type to continue with when the operand type is invalid
+    pub fn fallback_ty(&self) -> Type {
+        match self {
+            UnaryOp::Not => Type::Bool,
+            UnaryOp::Negate | UnaryOp::Complement => Type::Int(IntType::I64),
+        }
+    }
 }
 
 impl Display for UnaryOp {
@@ -11,6 +44,7 @@ impl Display for UnaryOp {
         match self {
             UnaryOp::Negate => write!(f, "-"),
             UnaryOp::Not => write!(f, "!"),
+            UnaryOp::Complement => write!(f, "~"),
         }
     }
 }
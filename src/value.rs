@@ -39,6 +39,13 @@ impl BoolValue {
             (_, BoolValue::Any) => self.clone(),
         }
     }
+
+    pub fn not(&self) -> BoolValue {
+        match self {
+            BoolValue::Constant(a) => BoolValue::Constant(!a),
+            _ => self.clone(),
+        }
+    }
 }
 
 impl Display for BoolValue {
@@ -51,45 +58,415 @@ impl Display for BoolValue {
     }
 }
 
+// bits known to be 0 and bits known to be 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownBits {
+    pub zeros: u64,
+    pub ones: u64,
+}
+
+impl KnownBits {
+    pub const UNKNOWN: KnownBits = KnownBits { zeros: 0, ones: 0 };
+
+    pub fn constant(a: i64) -> KnownBits {
+        KnownBits {
+            zeros: !a as u64,
+            ones: a as u64,
+        }
+    }
+
+    // the high bits that every value in [min, max] has in common
+    pub fn of_range(min: i64, max: i64) -> KnownBits {
+        let differ = (min ^ max) as u64;
+        let mask = match differ.checked_ilog2() {
+            Some(63) => 0,
+            Some(bit) => u64::MAX << (bit + 1),
+            None => u64::MAX,
+        };
+        KnownBits {
+            zeros: !min as u64 & mask,
+            ones: min as u64 & mask,
+        }
+    }
+
+    pub fn known(&self) -> u64 {
+        self.zeros | self.ones
+    }
+
+    // smallest and largest value with these bits
+    pub fn bounds(&self) -> (i64, i64) {
+        let sign = 1 << 63;
+        let min = self.ones | (!self.zeros & sign);
+        let max = (!self.zeros & !sign) | (self.ones & sign);
+        (min as i64, max as i64)
+    }
+
+    pub fn meet(&self, other: &KnownBits) -> KnownBits {
+        KnownBits {
+            zeros: self.zeros & other.zeros,
+            ones: self.ones & other.ones,
+        }
+    }
+
+    // None if the bits contradict each other
+    pub fn join(&self, other: &KnownBits) -> Option<KnownBits> {
+        let bits = KnownBits {
+            zeros: self.zeros | other.zeros,
+            ones: self.ones | other.ones,
+        };
+        if bits.zeros & bits.ones != 0 {
+            None
+        } else {
+            Some(bits)
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum IntValue {
     All,
     Constant(i64),
+    // somewhere in [min, max] with min < max, and agreeing with the known bits
+    Range { min: i64, max: i64, bits: KnownBits },
     Any,
 }
 
 impl IntValue {
+    // a value somewhere in [min, max] with the known bits, narrowing one by the other
+    pub fn new(min: i64, max: i64, bits: KnownBits) -> IntValue {
+        let bits = match bits.join(&KnownBits::of_range(min, max)) {
+            Some(bits) => bits,
+            None => return IntValue::All,
+        };
+        let (bits_min, bits_max) = bits.bounds();
+        let (min, max) = (min.max(bits_min), max.min(bits_max));
+        if min > max {
+            IntValue::All
+        } else if min == max {
+            IntValue::Constant(min)
+        } else if min == i64::MIN && max == i64::MAX && bits == KnownBits::UNKNOWN {
+            IntValue::Any
+        } else {
+            IntValue::Range { min, max, bits }
+        }
+    }
+
+    // a value somewhere in [min, max]
+    pub fn range(min: i64, max: i64) -> IntValue {
+        IntValue::new(min, max, KnownBits::UNKNOWN)
+    }
+
+    // a value with the known bits
+    pub fn from_bits(bits: KnownBits) -> IntValue {
+        IntValue::new(i64::MIN, i64::MAX, bits)
+    }
+
+    // smallest and largest possible value
+    pub fn bounds(&self) -> Option<(i64, i64)> {
+        match self {
+            IntValue::All => None,
+            IntValue::Constant(a) => Some((*a, *a)),
+            IntValue::Range { min, max, .. } => Some((*min, *max)),
+            IntValue::Any => Some((i64::MIN, i64::MAX)),
+        }
+    }
+
+    pub fn known_bits(&self) -> Option<KnownBits> {
+        match self {
+            IntValue::All => None,
+            IntValue::Constant(a) => Some(KnownBits::constant(*a)),
+            IntValue::Range { bits, .. } => Some(*bits),
+            IntValue::Any => Some(KnownBits::UNKNOWN),
+        }
+    }
+
     pub fn meet(&self, other: &IntValue) -> IntValue {
-        match (self, other) {
-            (IntValue::All, _) => other.clone(),
-            (_, IntValue::All) => self.clone(),
-            (IntValue::Constant(a), IntValue::Constant(b)) => {
-                if a == b {
-                    IntValue::Constant(*a)
+        match (
+            self.bounds(),
+            other.bounds(),
+            self.known_bits(),
+            other.known_bits(),
+        ) {
+            (Some((a_min, a_max)), Some((b_min, b_max)), Some(a_bits), Some(b_bits)) => {
+                IntValue::new(a_min.min(b_min), a_max.max(b_max), a_bits.meet(&b_bits))
+            }
+            (None, ..) => other.clone(),
+            _ => self.clone(),
+        }
+    }
+
+    pub fn join(&self, other: &IntValue) -> IntValue {
+        match (
+            self.bounds(),
+            other.bounds(),
+            self.known_bits(),
+            other.known_bits(),
+        ) {
+            (Some((a_min, a_max)), Some((b_min, b_max)), Some(a_bits), Some(b_bits)) => {
+                match a_bits.join(&b_bits) {
+                    Some(bits) => IntValue::new(a_min.max(b_min), a_max.min(b_max), bits),
+                    None => IntValue::All,
+                }
+            }
+            _ => IntValue::All,
+        }
+    }
+
+    fn with_bits(
+        &self,
+        other: &IntValue,
+        f: impl Fn(KnownBits, KnownBits) -> KnownBits,
+    ) -> IntValue {
+        match (self.known_bits(), other.known_bits()) {
+            (Some(a), Some(b)) => IntValue::from_bits(f(a, b)),
+            _ => IntValue::Any,
+        }
+    }
+
+    fn with_bounds(
+        &self,
+        other: &IntValue,
+        f: impl Fn((i64, i64), (i64, i64)) -> IntValue,
+    ) -> IntValue {
+        match (self.bounds(), other.bounds()) {
+            (Some(a), Some(b)) => f(a, b),
+            _ => IntValue::Any,
+        }
+    }
+
+    pub fn add(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            corners(&[a_min.checked_add(b_min), a_max.checked_add(b_max)])
+        })
+    }
+
+    pub fn sub(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            corners(&[a_min.checked_sub(b_max), a_max.checked_sub(b_min)])
+        })
+    }
+
+    pub fn mul(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            corners(&[
+                a_min.checked_mul(b_min),
+                a_min.checked_mul(b_max),
+                a_max.checked_mul(b_min),
+                a_max.checked_mul(b_max),
+            ])
+        })
+    }
+
+    pub fn div(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            if b_min <= 0 && b_max >= 0 {
+                return IntValue::Any;
+            }
+            corners(&[
+                a_min.checked_div(b_min),
+                a_min.checked_div(b_max),
+                a_max.checked_div(b_min),
+                a_max.checked_div(b_max),
+            ])
+        })
+    }
+
+    pub fn rem(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            if b_min <= 0 && b_max >= 0 {
+                return IntValue::Any;
+            }
+            // the remainder is smaller than the divisor, with the sign of the dividend
+            let m = (b_min.unsigned_abs().max(b_max.unsigned_abs()) - 1) as i64;
+            let min = if a_min >= 0 { 0 } else { a_min.max(-m) };
+            let max = if a_max <= 0 { 0 } else { a_max.min(m) };
+            IntValue::range(min, max)
+        })
+    }
+
+    pub fn and(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            // masking with a non-negative value gives something between 0 and that value
+            match (a_min >= 0, b_min >= 0) {
+                (true, true) => IntValue::range(0, a_max.min(b_max)),
+                (true, false) => IntValue::range(0, a_max),
+                (false, true) => IntValue::range(0, b_max),
+                (false, false) => IntValue::Any,
+            }
+        })
+        .join(&self.with_bits(other, |a, b| KnownBits {
+            zeros: a.zeros | b.zeros,
+            ones: a.ones & b.ones,
+        }))
+    }
+
+    pub fn or(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            if a_min >= 0 && b_min >= 0 {
+                IntValue::range(a_min.max(b_min), fill(a_max.max(b_max)))
+            } else {
+                IntValue::Any
+            }
+        })
+        .join(&self.with_bits(other, |a, b| KnownBits {
+            zeros: a.zeros & b.zeros,
+            ones: a.ones | b.ones,
+        }))
+    }
+
+    pub fn xor(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            if a_min >= 0 && b_min >= 0 {
+                IntValue::range(0, fill(a_max.max(b_max)))
+            } else {
+                IntValue::Any
+            }
+        })
+        .join(&self.with_bits(other, |a, b| {
+            let known = a.known() & b.known();
+            let ones = a.ones ^ b.ones;
+            KnownBits {
+                zeros: !ones & known,
+                ones: ones & known,
+            }
+        }))
+    }
+
+    pub fn shl(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            if b_min != b_max {
+                return IntValue::Any;
+            }
+            // shift amounts are taken modulo 64, shifting bits out is unknown
+            let shift = b_min as u32 & 63;
+            let shl = |a: i64| {
+                let result = a.wrapping_shl(shift);
+                if result >> shift == a {
+                    Some(result)
                 } else {
-                    IntValue::Any
+                    None
+                }
+            };
+            corners(&[shl(a_min), shl(a_max)])
+        })
+        .join(&self.with_bits(other, |a, b| match shift_amount(b) {
+            // shifted in bits are zero
+            Some(shift) => KnownBits {
+                zeros: (a.zeros << shift) | ((1 << shift) - 1),
+                ones: a.ones << shift,
+            },
+            None => KnownBits::UNKNOWN,
+        }))
+    }
+
+    pub fn shr(&self, other: &IntValue) -> IntValue {
+        self.with_bounds(other, |(a_min, a_max), (b_min, b_max)| {
+            let (b_min, b_max) = if b_min == b_max {
+                (b_min & 63, b_min & 63)
+            } else if b_min >= 0 && b_max <= 63 {
+                (b_min, b_max)
+            } else {
+                return IntValue::Any;
+            };
+            corners(&[
+                Some(a_min >> b_min),
+                Some(a_min >> b_max),
+                Some(a_max >> b_min),
+                Some(a_max >> b_max),
+            ])
+        })
+        .join(&self.with_bits(other, |a, b| match shift_amount(b) {
+            // shifted in bits are copies of the sign bit
+            Some(shift) => KnownBits {
+                zeros: ((a.zeros as i64) >> shift) as u64,
+                ones: ((a.ones as i64) >> shift) as u64,
+            },
+            None => KnownBits::UNKNOWN,
+        }))
+    }
+
+    pub fn neg(&self) -> IntValue {
+        match self.bounds() {
+            Some((min, max)) if min != i64::MIN => IntValue::range(-max, -min),
+            _ => IntValue::Any,
+        }
+    }
+
+    pub fn complement(&self) -> IntValue {
+        match self.bounds() {
+            Some((min, max)) => IntValue::range(!max, !min),
+            None => IntValue::Any,
+        }
+    }
+
+    pub fn equal(&self, other: &IntValue) -> BoolValue {
+        match (
+            self.bounds(),
+            other.bounds(),
+            self.known_bits(),
+            other.known_bits(),
+        ) {
+            (Some((a_min, a_max)), Some((b_min, b_max)), Some(a_bits), Some(b_bits)) => {
+                if a_min == a_max && b_min == b_max && a_min == b_min {
+                    BoolValue::Constant(true)
+                } else if a_max < b_min || b_max < a_min || a_bits.join(&b_bits).is_none() {
+                    BoolValue::Constant(false)
+                } else {
+                    BoolValue::Any
                 }
             }
-            (IntValue::Any, _) => IntValue::Any,
-            (_, IntValue::Any) => IntValue::Any,
+            _ => BoolValue::Any,
         }
     }
 
-    pub fn join(&self, other: &IntValue) -> IntValue {
-        match (self, other) {
-            (IntValue::All, _) => IntValue::All,
-            (_, IntValue::All) => IntValue::All,
-            (IntValue::Constant(a), IntValue::Constant(b)) => {
-                if a == b {
-                    IntValue::Constant(*a)
+    pub fn less_than(&self, other: &IntValue) -> BoolValue {
+        match (self.bounds(), other.bounds()) {
+            (Some((a_min, a_max)), Some((b_min, b_max))) => {
+                if a_max < b_min {
+                    BoolValue::Constant(true)
+                } else if a_min >= b_max {
+                    BoolValue::Constant(false)
                 } else {
-                    IntValue::Any
+                    BoolValue::Any
                 }
             }
-            (IntValue::Any, _) => other.clone(),
-            (_, IntValue::Any) => self.clone(),
+            _ => BoolValue::Any,
+        }
+    }
+}
+
+// the range spanned by the results at the corners, unknown if any of them overflows
+fn corners(values: &[Option<i64>]) -> IntValue {
+    let mut min = i64::MAX;
+    let mut max = i64::MIN;
+    for value in values.iter() {
+        match value {
+            Some(value) => {
+                min = min.min(*value);
+                max = max.max(*value);
+            }
+            None => return IntValue::Any,
         }
     }
+    IntValue::range(min, max)
+}
+
+// the shift amount, if all of its used bits are known
+fn shift_amount(bits: KnownBits) -> Option<u32> {
+    if bits.known() & 63 == 63 {
+        Some((bits.ones & 63) as u32)
+    } else {
+        None
+    }
+}
+
+// all bits up to and including the highest bit of a non-negative value
+fn fill(value: i64) -> i64 {
+    match value.checked_ilog2() {
+        Some(bit) if bit < 62 => (2 << bit) - 1,
+        Some(_) => i64::MAX,
+        None => 0,
+    }
 }
 
 impl Display for IntValue {
@@ -97,16 +474,159 @@ impl Display for IntValue {
         match self {
             IntValue::All => write!(f, "int(all)"),
             IntValue::Constant(a) => write!(f, "{}", a),
+            IntValue::Range { min, max, bits } => {
+                if *bits == KnownBits::of_range(*min, *max) {
+                    write!(f, "int({}..={})", min, max)
+                } else {
+                    write!(
+                        f,
+                        "int({}..={}, zeros {:#x}, ones {:#x})",
+                        min, max, bits.zeros, bits.ones
+                    )
+                }
+            }
             IntValue::Any => write!(f, "int(any)"),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum FloatValue {
+    All,
+    Constant(f64),
+    // any value except NaN
+    NotNaN,
+    Any,
+}
+
+impl FloatValue {
+    pub fn is_nan(&self) -> Option<bool> {
+        match self {
+            FloatValue::All => None,
+            FloatValue::Constant(a) => Some(a.is_nan()),
+            FloatValue::NotNaN => Some(false),
+            FloatValue::Any => None,
+        }
+    }
+
+    pub fn meet(&self, other: &FloatValue) -> FloatValue {
+        match (self, other) {
+            (FloatValue::All, _) => *other,
+            (_, FloatValue::All) => *self,
+            _ if self == other => *self,
+            _ if self.is_nan() == Some(false) && other.is_nan() == Some(false) => {
+                FloatValue::NotNaN
+            }
+            _ => FloatValue::Any,
+        }
+    }
+
+    pub fn join(&self, other: &FloatValue) -> FloatValue {
+        match (self, other) {
+            (FloatValue::Any, _) => *other,
+            (_, FloatValue::Any) => *self,
+            _ if self == other => *self,
+            (FloatValue::NotNaN, FloatValue::Constant(a))
+            | (FloatValue::Constant(a), FloatValue::NotNaN)
+                if !a.is_nan() =>
+            {
+                FloatValue::Constant(*a)
+            }
+            _ => FloatValue::All,
+        }
+    }
+
+    // a constant other than NaN or an infinity
+    fn finite(&self) -> Option<f64> {
+        match self {
+            FloatValue::Constant(a) if a.is_finite() => Some(*a),
+            _ => None,
+        }
+    }
+
+    // NaN only comes out of inf - inf, so adding a finite constant to something that isn't NaN
+    // is fine
+    pub fn add(&self, other: &FloatValue) -> FloatValue {
+        if (self.finite().is_some() && other.is_nan() == Some(false))
+            || (other.finite().is_some() && self.is_nan() == Some(false))
+        {
+            FloatValue::NotNaN
+        } else {
+            FloatValue::Any
+        }
+    }
+
+    // NaN only comes out of 0 * inf
+    pub fn mul(&self, other: &FloatValue) -> FloatValue {
+        let nonzero = |value: &FloatValue| value.finite().is_some_and(|a| a != 0.0);
+        if (nonzero(self) && other.is_nan() == Some(false))
+            || (nonzero(other) && self.is_nan() == Some(false))
+        {
+            FloatValue::NotNaN
+        } else {
+            FloatValue::Any
+        }
+    }
+
+    // NaN only comes out of 0 / 0 and inf / inf
+    pub fn div(&self, other: &FloatValue) -> FloatValue {
+        if self.is_nan() == Some(false) && other.finite().is_some_and(|a| a != 0.0) {
+            FloatValue::NotNaN
+        } else {
+            FloatValue::Any
+        }
+    }
+
+    pub fn neg(&self) -> FloatValue {
+        match self {
+            FloatValue::Constant(a) => FloatValue::Constant(-a),
+            _ => *self,
+        }
+    }
+
+    // comparisons with NaN are false, except for !=
+    pub fn compare(&self, other: &FloatValue) -> BoolValue {
+        if self.is_nan() == Some(true) || other.is_nan() == Some(true) {
+            BoolValue::Constant(false)
+        } else {
+            BoolValue::Any
+        }
+    }
+}
+
+// constants are equal when they have the same bits, so NaN is a constant like any other and
+// 0.0 and -0.0 are different
+impl PartialEq for FloatValue {
+    fn eq(&self, other: &FloatValue) -> bool {
+        match (self, other) {
+            (FloatValue::All, FloatValue::All) => true,
+            (FloatValue::Constant(a), FloatValue::Constant(b)) => a.to_bits() == b.to_bits(),
+            (FloatValue::NotNaN, FloatValue::NotNaN) => true,
+            (FloatValue::Any, FloatValue::Any) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for FloatValue {}
+
+impl Display for FloatValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FloatValue::All => write!(f, "float(all)"),
+            FloatValue::Constant(a) => write!(f, "{:?}", a),
+            FloatValue::NotNaN => write!(f, "float(not nan)"),
+            FloatValue::Any => write!(f, "float(any)"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Value {
     All,
     Bool(BoolValue),
     Int(IntValue),
+    Float(FloatValue),
     Any,
 }
 
@@ -117,9 +637,11 @@ impl Value {
             (_, Value::All) => self.clone(),
             (Value::Bool(a), Value::Bool(b)) => Value::Bool(a.meet(b)),
             (Value::Int(a), Value::Int(b)) => Value::Int(a.meet(b)),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a.meet(b)),
             (Value::Any, _) => Value::Any,
             (_, Value::Any) => Value::Any,
-            (_, _) => panic!("unable to meet {} with {}", self, other),
+            // values of different types have nothing in common
+            (_, _) => Value::Any,
         }
     }
 
@@ -129,9 +651,10 @@ impl Value {
             (_, Value::All) => Value::All,
             (Value::Bool(a), Value::Bool(b)) => Value::Bool(a.join(b)),
             (Value::Int(a), Value::Int(b)) => Value::Int(a.join(b)),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a.join(b)),
             (Value::Any, _) => other.clone(),
             (_, Value::Any) => self.clone(),
-            (_, _) => panic!("unable to join {} with {}", self, other),
+            (_, _) => Value::All,
         }
     }
 }
@@ -142,6 +665,7 @@ impl Display for Value {
             Value::All => write!(f, "all"),
             Value::Bool(b) => write!(f, "{}", b),
             Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
             Value::Any => write!(f, "any"),
         }
     }
@@ -163,3 +687,193 @@ impl Display for Data {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn range_normalization() {
+        assert_eq!(IntValue::range(3, 3), IntValue::Constant(3));
+        assert_eq!(IntValue::range(5, 3), IntValue::All);
+        assert_eq!(IntValue::range(i64::MIN, i64::MAX), IntValue::Any);
+    }
+
+    #[test]
+    fn range_meet_and_join() {
+        let odd = IntValue::Constant(1).meet(&IntValue::Constant(3));
+        assert_eq!(odd.bounds(), Some((1, 3)));
+        assert_eq!(odd.known_bits().unwrap().ones, 1);
+        assert_eq!(
+            IntValue::All.meet(&IntValue::Constant(7)),
+            IntValue::Constant(7)
+        );
+
+        let a = IntValue::range(0, 10);
+        assert_eq!(a.join(&IntValue::range(5, 20)), IntValue::range(5, 10));
+        assert_eq!(a.join(&IntValue::range(11, 20)), IntValue::All);
+        assert_eq!(a.join(&IntValue::Any), a);
+    }
+
+    #[test]
+    fn range_arithmetic() {
+        let (a, b) = (IntValue::range(1, 3), IntValue::range(10, 20));
+        assert_eq!(a.add(&b), IntValue::range(11, 23));
+        assert_eq!(a.sub(&b), IntValue::range(-19, -7));
+        assert_eq!(
+            IntValue::range(-2, 3).mul(&IntValue::range(-4, 5)),
+            IntValue::range(-12, 15)
+        );
+        assert_eq!(b.div(&IntValue::range(2, 5)), IntValue::range(2, 10));
+        assert_eq!(b.div(&IntValue::range(-1, 1)), IntValue::Any);
+        assert_eq!(
+            IntValue::range(-7, 7).rem(&IntValue::Constant(4)),
+            IntValue::range(-3, 3)
+        );
+        assert_eq!(
+            IntValue::range(2, 5).rem(&IntValue::Constant(10)),
+            IntValue::range(0, 5)
+        );
+        assert_eq!(a.neg(), IntValue::range(-3, -1));
+        assert_eq!(IntValue::range(0, 5).complement(), IntValue::range(-6, -1));
+    }
+
+    #[test]
+    fn range_overflow() {
+        let max = IntValue::Constant(i64::MAX);
+        let min = IntValue::Constant(i64::MIN);
+        assert_eq!(max.add(&IntValue::Constant(1)), IntValue::Any);
+        assert_eq!(min.sub(&IntValue::Constant(1)), IntValue::Any);
+        assert_eq!(max.mul(&IntValue::range(1, 2)), IntValue::Any);
+        assert_eq!(min.div(&IntValue::Constant(-1)), IntValue::Any);
+        assert_eq!(min.neg(), IntValue::Any);
+    }
+
+    #[test]
+    fn range_comparisons() {
+        let a = IntValue::range(0, 5);
+        assert_eq!(
+            a.less_than(&IntValue::range(6, 9)),
+            BoolValue::Constant(true)
+        );
+        assert_eq!(a.less_than(&IntValue::range(5, 9)), BoolValue::Any);
+        assert_eq!(
+            IntValue::range(6, 9).less_than(&IntValue::range(0, 6)),
+            BoolValue::Constant(false)
+        );
+        assert_eq!(
+            IntValue::Constant(3).equal(&IntValue::Constant(3)),
+            BoolValue::Constant(true)
+        );
+        assert_eq!(a.equal(&IntValue::range(6, 9)), BoolValue::Constant(false));
+        assert_eq!(a.equal(&IntValue::Constant(3)), BoolValue::Any);
+    }
+
+    #[test]
+    fn known_bits() {
+        let bits = KnownBits::of_range(8, 15);
+        assert_eq!((bits.zeros, bits.ones), (!15, 8));
+        assert_eq!(KnownBits::of_range(-1, 0), KnownBits::UNKNOWN);
+        let byte = KnownBits {
+            zeros: !0xff,
+            ones: 0,
+        };
+        assert_eq!(byte.bounds(), (0, 255));
+        assert_eq!(KnownBits::constant(5).join(&KnownBits::constant(6)), None);
+        let odd = KnownBits { zeros: 0, ones: 1 };
+        assert_eq!(IntValue::new(0, 100, odd).bounds(), Some((1, 100)));
+    }
+
+    #[test]
+    fn bitwise_known_bits() {
+        let masked = IntValue::Any.and(&IntValue::Constant(0xf0));
+        assert_eq!(masked.bounds(), Some((0, 0xf0)));
+        assert_eq!(masked.known_bits().unwrap().zeros, !0xf0);
+
+        let odd = IntValue::Any.or(&IntValue::Constant(1));
+        assert_eq!(
+            odd.equal(&IntValue::Constant(0)),
+            BoolValue::Constant(false)
+        );
+        assert_eq!(odd.equal(&IntValue::Constant(7)), BoolValue::Any);
+
+        assert_eq!(
+            IntValue::Constant(5).xor(&IntValue::Constant(3)),
+            IntValue::Constant(6)
+        );
+    }
+
+    #[test]
+    fn shifts() {
+        let shifted = IntValue::Any.shl(&IntValue::Constant(4));
+        assert_eq!(shifted.known_bits().unwrap().zeros & 15, 15);
+        assert_eq!(
+            shifted.equal(&IntValue::Constant(8)),
+            BoolValue::Constant(false)
+        );
+        assert_eq!(
+            IntValue::Constant(3).shl(&IntValue::Constant(2)),
+            IntValue::Constant(12)
+        );
+        assert_eq!(
+            IntValue::Constant(-16).shr(&IntValue::Constant(2)),
+            IntValue::Constant(-4)
+        );
+        assert_eq!(
+            IntValue::range(0, 255).shr(&IntValue::Constant(4)),
+            IntValue::range(0, 15)
+        );
+        assert_eq!(
+            IntValue::Any.shr(&IntValue::Constant(63)).bounds(),
+            Some((-1, 0))
+        );
+    }
+
+    #[test]
+    fn float_lattice() {
+        let nan = FloatValue::Constant(f64::NAN);
+        let one = FloatValue::Constant(1.0);
+        assert_eq!(one.meet(&FloatValue::Constant(2.0)), FloatValue::NotNaN);
+        assert_eq!(nan.meet(&one), FloatValue::Any);
+        assert_eq!(
+            FloatValue::All.meet(&FloatValue::NotNaN),
+            FloatValue::NotNaN
+        );
+        assert_eq!(FloatValue::NotNaN.join(&one), one);
+        assert_eq!(FloatValue::NotNaN.join(&nan), FloatValue::All);
+        assert_eq!(FloatValue::Any.join(&nan), nan);
+
+        // constants compare by their bits
+        assert_eq!(nan, FloatValue::Constant(f64::NAN));
+        assert_ne!(FloatValue::Constant(0.0), FloatValue::Constant(-0.0));
+    }
+
+    #[test]
+    fn float_nan_tracking() {
+        let not_nan = FloatValue::NotNaN;
+        let two = FloatValue::Constant(2.0);
+        assert_eq!(not_nan.add(&two), FloatValue::NotNaN);
+        assert_eq!(not_nan.add(&not_nan), FloatValue::Any);
+        assert_eq!(
+            FloatValue::Constant(f64::INFINITY).add(&not_nan),
+            FloatValue::Any
+        );
+        assert_eq!(not_nan.mul(&two), FloatValue::NotNaN);
+        assert_eq!(not_nan.mul(&FloatValue::Constant(0.0)), FloatValue::Any);
+        assert_eq!(not_nan.div(&two), FloatValue::NotNaN);
+        assert_eq!(two.div(&not_nan), FloatValue::Any);
+        assert_eq!(two.neg(), FloatValue::Constant(-2.0));
+        assert_eq!(not_nan.neg(), FloatValue::NotNaN);
+    }
+
+    #[test]
+    fn float_compare() {
+        let nan = FloatValue::Constant(f64::NAN);
+        assert_eq!(nan.compare(&FloatValue::Any), BoolValue::Constant(false));
+        assert_eq!(FloatValue::NotNaN.compare(&nan), BoolValue::Constant(false));
+        assert_eq!(
+            FloatValue::NotNaN.compare(&FloatValue::Constant(1.0)),
+            BoolValue::Any
+        );
+    }
+}